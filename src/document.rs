@@ -0,0 +1,1239 @@
+use std::error::Error;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use toml::Value;
+use toml_edit::{Array, ArrayOfTables, DocumentMut, ImDocument, InlineTable, Item, Table, TableLike};
+
+use crate::format::{reorder_inline_table, split_blank};
+use crate::operations::{array_mut, conflict_marker, integer_literals, parse_path, Conflict, Segment};
+
+/// A TOML file as it was read from disk, kept alongside its parsed `Value` so
+/// that unchanged parts keep their formatting when the file is written back.
+pub struct Document {
+    pub edit: DocumentMut,
+    pub value: Value,
+    /// Values as typed on the command line, by key, whose integer notation
+    /// should survive the write (see `keep_integer_literal`).
+    pub literals: Vec<(String, String)>,
+    /// How tables created by this change are written; see `TableStyle`.
+    pub style: Option<TableStyle>,
+    /// A key written by this change and where it goes among its siblings;
+    /// when that creates tables, the outermost of them is placed instead.
+    pub placement: Option<(String, Placement)>,
+}
+
+/// The ways a new table can be written. Without one, tables get a `[header]`
+/// and arrays of tables are written as `[[header]]` entries when new, and
+/// otherwise keep the style they have.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TableStyle {
+    /// `[a]`, with tables appended to an inline array of tables turning it
+    /// into `[[a]]` entries.
+    Header,
+    /// `a = { ... }`, and arrays of tables as `a = [{ ... }]`.
+    Inline,
+    /// `a.b.c = ...` lines in the body of the parent table.
+    Dotted,
+}
+
+/// Where a key goes among the other keys of its table.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Placement {
+    First,
+    Last,
+    Before(String),
+    After(String),
+}
+
+/// Reads the document at `path`, which must exist.
+pub fn load(path: &str) -> Result<Document, Box<dyn Error>> {
+    if !Path::new(path).exists() {
+        return Err(format!("{} does not exist", path).into());
+    }
+    load_or_new(path)
+}
+
+/// Like `load`, with a missing file read as an empty document, for commands
+/// that create the file when they write to it.
+pub fn load_or_new(path: &str) -> Result<Document, Box<dyn Error>> {
+    let content = if Path::new(path).exists() {
+        fs::read_to_string(path)?
+    } else {
+        String::new()
+    };
+
+    if content.is_empty() {
+        return Ok(Document { edit: DocumentMut::new(), value: Value::Table(toml::Table::new()), literals: Vec::new(), style: None, placement: None });
+    }
+    Ok(Document { edit: content.parse()?, value: toml::from_str(&content)?, literals: Vec::new(), style: None, placement: None })
+}
+
+pub fn save(path: &str, document: &mut Document) -> Result<(), Box<dyn Error>> {
+    let placed = document.placement.as_ref()
+        .and_then(|(key, placement)| Some((key, created(&mut document.edit, key)?, placement)));
+    sync_styled(&mut document.edit, &document.value, document.style);
+    for (key, literal) in &document.literals {
+        for (path, literal) in integer_literals(literal) {
+            keep_integer_literal(&mut document.edit, &format!("{}{}", key, path), &literal);
+        }
+    }
+    if let Some((key, segments, placement)) = placed {
+        place(&mut document.edit, key, &segments, placement)?;
+    }
+    fs::write(path, document.edit.to_string())?;
+    Ok(())
+}
+
+/// Writes the elements of array `key` at `inserted` (see `insert_parsed_value`)
+/// into the formatted document at the same place, so that the elements after
+/// them keep their comments and notation instead of being matched by index.
+/// They are appended in the style of the array, then moved into place.
+pub fn insert_elements(document: &mut Document, key: &str, inserted: Range<usize>) -> Result<(), Box<dyn Error>> {
+    let mut appended = document.value.clone();
+    let array = array_mut(&mut appended, key)?;
+    let new: Vec<Value> = array.drain(inserted.clone()).collect();
+    array.extend(new);
+    let old_len = array.len() - inserted.len();
+    sync_styled(&mut document.edit, &appended, document.style);
+
+    let order: Vec<usize> = (0..inserted.start)
+        .chain(old_len..old_len + inserted.len())
+        .chain(inserted.start..old_len)
+        .collect();
+    crate::format::rearrange(&mut document.edit, key, &order)
+}
+
+/// Writes the value copied or moved from `from` to `to` (see `copy_value`)
+/// the way it is written at `from`: with its notation, and with its comments
+/// and the comments above its key when both are keys of `[header]` tables.
+pub fn copy_item(document: &mut Document, from: &str, to: &str) {
+    let source_segments = parse_path(from);
+    let Some(source) = node(&document.edit, from).map(|node| match node {
+        Node::Item(item) => item.clone(),
+        Node::Table(table) => Item::Table(table.clone()),
+        Node::Value(value) => Item::Value(value.clone()),
+    }) else {
+        return;
+    };
+    let source_key = key_in_table(&mut document.edit, &source_segments).map(|(table, key)| table.key(key).map(|key| key.leaf_decor().clone()));
+    sync_styled(&mut document.edit, &document.value, document.style);
+
+    let segments = parse_path(to);
+    // Comments only fit between the keys of a `[header]` table.
+    let decor = match (source_key, key_in_table(&mut document.edit, &segments)) {
+        (Some(key_decor), Some((table, key))) => {
+            if let (Some(key_decor), Some(mut key)) = (key_decor, table.key_mut(key)) {
+                *key.leaf_decor_mut() = key_decor;
+            }
+            true
+        },
+        _ => false,
+    };
+    match (node_at(&mut document.edit, &segments), &source) {
+        (Some(NodeMut::Value(current)), Item::Value(value)) => {
+            let current_decor = current.decor().clone();
+            *current = value.clone();
+            if !decor {
+                *current.decor_mut() = current_decor;
+            }
+        },
+        (Some(NodeMut::Table(current) | NodeMut::Item(Item::Table(current))), Item::Table(table)) => copy_table(current, table),
+        (Some(NodeMut::Item(Item::ArrayOfTables(current))), Item::ArrayOfTables(array)) => {
+            for (current, table) in current.iter_mut().zip(array.iter()) {
+                copy_table(current, table);
+            }
+        },
+        _ => {},
+    }
+}
+
+/// The table holding the last key of `segments` and that key, when it is a
+/// key of a `[header]` table rather than of an inline table or an array.
+fn key_in_table<'a, 'b>(edit: &'a mut DocumentMut, segments: &'b [Segment]) -> Option<(&'a mut Table, &'b str)> {
+    let (Segment::Key(key), parent) = segments.split_last()? else {
+        return None;
+    };
+    match node_at(edit, parent)? {
+        NodeMut::Table(table) | NodeMut::Item(Item::Table(table)) => Some((table, key.as_ref())),
+        _ => None,
+    }
+}
+
+/// Copies the comments, notation and key order of `source` onto `table`,
+/// which holds the same values. `table` keeps its place in the file.
+fn copy_table(table: &mut Table, source: &Table) {
+    *table.decor_mut() = source.decor().clone();
+    table.set_implicit(source.is_implicit());
+    table.set_dotted(source.is_dotted());
+    for (key, item) in source.iter() {
+        if let Some(mut current) = table.key_mut(key) {
+            *current.leaf_decor_mut() = source.key(key).unwrap().leaf_decor().clone();
+        }
+        match (table.get_mut(key), item) {
+            (Some(Item::Value(current)), Item::Value(value)) => *current = value.clone(),
+            (Some(current), item) => copy_tables(current, item),
+            _ => {},
+        }
+    }
+    let order: Vec<&str> = source.iter().map(|(key, _)| key).collect();
+    let rank = |key: &toml_edit::Key| order.iter().position(|k| *k == key.get()).unwrap_or(order.len());
+    table.sort_values_by(|a, _, b, _| rank(a).cmp(&rank(b)));
+}
+
+fn copy_tables(item: &mut Item, source: &Item) {
+    match (item, source) {
+        (Item::Table(table), Item::Table(source)) => copy_table(table, source),
+        (Item::ArrayOfTables(array), Item::ArrayOfTables(source)) => {
+            for (table, source) in array.iter_mut().zip(source.iter()) {
+                copy_table(table, source);
+            }
+        },
+        _ => {},
+    }
+}
+
+/// Lays out `value` as a new document, the way new tables and keys are written
+/// into existing files.
+pub fn render(value: &Value) -> String {
+    let mut edit = DocumentMut::new();
+    sync(&mut edit, value);
+    edit.to_string()
+}
+
+/// Writes out `edit`, a merge holding each conflict's marker (see `merge3`),
+/// with a git-style conflict block in place of every statement holding one:
+/// the whole top-level `key = value`, as written with our and their values,
+/// so that keeping either side leaves valid TOML.
+pub fn mark_conflicts(edit: &DocumentMut, conflicts: &[Conflict]) -> String {
+    let markers: Vec<String> = (0..conflicts.len()).map(conflict_marker).collect();
+    let side = |pick: fn(&Conflict) -> &Option<Value>| {
+        let mut edit = edit.clone();
+        let sides: Vec<(&str, Option<&Value>)> = markers.iter()
+            .zip(conflicts)
+            .map(|(marker, conflict)| (marker.as_str(), pick(conflict).as_ref()))
+            .collect();
+        resolve_markers(edit.as_table_mut(), &sides);
+        edit.to_string()
+    };
+    let (text, ours, theirs) = (edit.to_string(), side(|conflict| &conflict.ours), side(|conflict| &conflict.theirs));
+    let [parsed, parsed_ours, parsed_theirs] = [&text, &ours, &theirs]
+        .map(|text| ImDocument::parse(text.as_str()).expect("a document written by toml_edit"));
+
+    let mut statements = Vec::new();
+    for marker in &markers {
+        if let Some(statement) = find_statement(parsed.as_table(), marker, &mut Vec::new()) {
+            if !statements.contains(&statement) {
+                statements.push(statement);
+            }
+        }
+    }
+    statements.sort_by_key(|(_, span)| span.start);
+
+    let mut marked = String::new();
+    let mut copied = 0;
+    for (path, span) in &statements {
+        let lines = line_range(&text, span.clone());
+        let side = |parsed: &ImDocument<&str>, text: &str| {
+            statement_span(parsed.as_table(), path).map_or(String::new(), |span| text[line_range(text, span)].to_string())
+        };
+        marked.push_str(&text[copied..lines.start]);
+        marked.push_str("<<<<<<< ours\n");
+        marked.push_str(&side(&parsed_ours, &ours));
+        marked.push_str("=======\n");
+        marked.push_str(&side(&parsed_theirs, &theirs));
+        marked.push_str(">>>>>>> theirs\n");
+        copied = lines.end;
+    }
+    marked.push_str(&text[copied..]);
+    marked
+}
+
+/// Replaces each marker in `sides` with its value, or removes its key where
+/// the value is `None`. Markers only ever stand for the value of a table key.
+fn resolve_markers(table: &mut dyn TableLike, sides: &[(&str, Option<&Value>)]) {
+    let mut removed = Vec::new();
+    for (key, item) in table.iter_mut() {
+        let side = item.as_str().and_then(|text| sides.iter().find(|(marker, _)| *marker == text));
+        match (side, item) {
+            (Some((_, Some(value))), Item::Value(current)) => {
+                let decor = current.decor().clone();
+                *current = new_value(value);
+                *current.decor_mut() = decor;
+            },
+            (Some((_, None)), _) => removed.push(key.get().to_string()),
+            (None, item) => {
+                if let Some(table) = item.as_table_like_mut() {
+                    resolve_markers(table, sides);
+                }
+            },
+            _ => {},
+        }
+    }
+    for key in removed {
+        table.remove(&key);
+    }
+}
+
+/// The keys leading to the top-level statement whose value holds `marker`,
+/// and the span of that value.
+fn find_statement(table: &Table, marker: &str, path: &mut Vec<String>) -> Option<(Vec<String>, std::ops::Range<usize>)> {
+    for (key, item) in table.iter() {
+        path.push(key.to_string());
+        let found = match item {
+            Item::Table(table) => find_statement(table, marker, path),
+            Item::Value(value) if holds_marker(value, marker) => Some((path.clone(), value.span()?)),
+            _ => None,
+        };
+        path.pop();
+        if found.is_some() {
+            return found;
+        }
+    }
+    None
+}
+
+fn holds_marker(value: &toml_edit::Value, marker: &str) -> bool {
+    match value {
+        toml_edit::Value::String(s) => s.value() == marker,
+        toml_edit::Value::InlineTable(table) => table.iter().any(|(_, value)| holds_marker(value, marker)),
+        _ => false,
+    }
+}
+
+fn statement_span(table: &Table, path: &[String]) -> Option<std::ops::Range<usize>> {
+    let (last, parents) = path.split_last()?;
+    let mut table = table;
+    for key in parents {
+        table = table.get(key)?.as_table()?;
+    }
+    table.get(last)?.as_value()?.span()
+}
+
+/// Widens `span` to the whole lines it is on, final newline included.
+fn line_range(text: &str, span: std::ops::Range<usize>) -> std::ops::Range<usize> {
+    let start = text[..span.start].rfind('\n').map_or(0, |pos| pos + 1);
+    let end = text[span.end..].find('\n').map_or(text.len(), |pos| span.end + pos + 1);
+    start..end
+}
+
+/// Brings `edit` in line with `value`, touching only what differs: existing
+/// keys keep their position, comments and notation (`0xFF`, `1_000`, ...).
+pub fn sync(edit: &mut DocumentMut, value: &Value) {
+    sync_styled(edit, value, None);
+}
+
+/// Like `sync`, writing the tables it creates in `style`.
+fn sync_styled(edit: &mut DocumentMut, value: &Value, style: Option<TableStyle>) {
+    if let Value::Table(table) = value {
+        sync_table(edit.as_table_mut(), table, style);
+    }
+}
+
+/// Rewrites the integer at `key` in the notation of `literal` (e.g. `0o755`),
+/// provided both denote the same number.
+pub fn keep_integer_literal(edit: &mut DocumentMut, key: &str, literal: &str) {
+    let Ok(toml_edit::Value::Integer(parsed)) = literal.trim().parse::<toml_edit::Value>() else {
+        return;
+    };
+    if let Some(NodeMut::Value(toml_edit::Value::Integer(current))) = node_mut(edit, key) {
+        if current.value() == parsed.value() {
+            let decor = current.decor().clone();
+            *current = parsed;
+            *current.decor_mut() = decor;
+        }
+    }
+}
+
+pub enum Node<'a> {
+    Item(&'a Item),
+    Table(&'a Table),
+    Value(&'a toml_edit::Value),
+}
+
+impl<'a> From<&'a Item> for Node<'a> {
+    fn from(item: &'a Item) -> Self {
+        match item {
+            Item::Value(value) => Node::Value(value),
+            item => Node::Item(item),
+        }
+    }
+}
+
+/// Like `node_mut`, for reading.
+pub fn node<'a>(edit: &'a DocumentMut, key: &str) -> Option<Node<'a>> {
+    let mut node = Node::Table(edit.as_table());
+    for segment in parse_path(key) {
+        node = child(node, &segment)?;
+    }
+    Some(node)
+}
+
+fn child<'a>(node: Node<'a>, segment: &Segment) -> Option<Node<'a>> {
+    match (node, segment) {
+        (Node::Item(Item::Table(table)), _) | (Node::Table(table), _) => match segment {
+            Segment::Key(key) => table.get(key).map(Node::from),
+            Segment::Index(_) => None,
+        },
+        (Node::Item(Item::ArrayOfTables(array)), Segment::Index(index)) => {
+            array.get(index.or(array.len().checked_sub(1))?).map(Node::Table)
+        },
+        (Node::Value(toml_edit::Value::InlineTable(table)), Segment::Key(key)) => table.get(key).map(Node::Value),
+        (Node::Value(toml_edit::Value::Array(array)), Segment::Index(index)) => {
+            array.get(index.or(array.len().checked_sub(1))?).map(Node::Value)
+        },
+        _ => None,
+    }
+}
+
+pub enum NodeMut<'a> {
+    Item(&'a mut Item),
+    Table(&'a mut Table),
+    Value(&'a mut toml_edit::Value),
+}
+
+impl<'a> From<&'a mut Item> for NodeMut<'a> {
+    fn from(item: &'a mut Item) -> Self {
+        match item {
+            Item::Value(value) => NodeMut::Value(value),
+            item => NodeMut::Item(item),
+        }
+    }
+}
+
+/// Finds the item at `key` using the same syntax as `get_value`; `[]` refers
+/// to the last element of an array.
+pub fn node_mut<'a>(edit: &'a mut DocumentMut, key: &str) -> Option<NodeMut<'a>> {
+    node_at(edit, &parse_path(key))
+}
+
+/// Like `node_mut`, for a key already split into segments.
+pub fn node_at<'a>(edit: &'a mut DocumentMut, segments: &[Segment]) -> Option<NodeMut<'a>> {
+    let mut node = NodeMut::Table(edit.as_table_mut());
+    for segment in segments {
+        node = child_mut(node, segment)?;
+    }
+    Some(node)
+}
+
+fn child_mut<'a>(node: NodeMut<'a>, segment: &Segment) -> Option<NodeMut<'a>> {
+    match (node, segment) {
+        (NodeMut::Item(Item::Table(table)), _) | (NodeMut::Table(table), _) => match segment {
+            Segment::Key(key) => table.get_mut(key).map(NodeMut::from),
+            Segment::Index(_) => None,
+        },
+        (NodeMut::Item(Item::ArrayOfTables(array)), Segment::Index(index)) => {
+            let index = index.or(array.len().checked_sub(1))?;
+            array.get_mut(index).map(NodeMut::Table)
+        },
+        (NodeMut::Value(toml_edit::Value::InlineTable(table)), Segment::Key(key)) => {
+            table.get_mut(key).map(NodeMut::Value)
+        },
+        (NodeMut::Value(toml_edit::Value::Array(array)), Segment::Index(index)) => {
+            let index = index.or(array.len().checked_sub(1))?;
+            array.get_mut(index).map(NodeMut::Value)
+        },
+        _ => None,
+    }
+}
+
+/// The segments of `key` down to the first one missing from `edit`: the
+/// key that writing `key` adds to an existing table. `None` if `key` exists
+/// already, as it then stays where it is.
+fn created<'a>(edit: &mut DocumentMut, key: &'a str) -> Option<Vec<Segment<'a>>> {
+    let mut segments = parse_path(key);
+    let missing = (1..=segments.len()).find(|&n| node_at(edit, &segments[..n]).is_none())?;
+    segments.truncate(missing);
+    Some(segments)
+}
+
+/// Moves the key at `segments` (a split `key`) to `placement` among the keys
+/// of its table. A `[header]` table or `[[array of tables]]` moves in the file
+/// too, along with the tables below it, when the place is next to other such
+/// tables.
+pub fn place(edit: &mut DocumentMut, key: &str, segments: &[Segment], placement: &Placement) -> Result<(), Box<dyn Error>> {
+    let Some((Segment::Key(name), segments)) = segments.split_last() else {
+        return Err(format!("{} is not a key of a table and cannot be placed", key).into());
+    };
+    match node_at(edit, segments) {
+        Some(NodeMut::Table(table) | NodeMut::Item(Item::Table(table))) if table.contains_key(name) => {
+            for sibling in sibling_order(table.iter().map(|(key, _)| key), key, name, placement)? {
+                let (sibling, item) = table.remove_entry(&sibling).unwrap();
+                table.insert_formatted(&sibling, item);
+            }
+        },
+        Some(NodeMut::Value(toml_edit::Value::InlineTable(table))) if table.contains_key(name) => {
+            let keys = sibling_order(table.iter().map(|(key, _)| key), key, name, placement)?;
+            reorder_inline_table(table, &keys);
+            return Ok(());
+        },
+        _ => return Err(format!("{} does not exist", key).into()),
+    }
+    move_tables(edit, segments, name, placement);
+    Ok(())
+}
+
+/// The keys of a table once `name` is moved to `placement`.
+fn sibling_order<'a>(keys: impl Iterator<Item = &'a str>, key: &str, name: &str, placement: &Placement) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut keys: Vec<String> = keys.filter(|sibling| *sibling != name).map(String::from).collect();
+    let position = |sibling: &String| {
+        keys.iter()
+            .position(|key| key == sibling)
+            .ok_or_else(|| format!("Cannot place {} next to {}, which is not in the same table", key, sibling))
+    };
+    let index = match placement {
+        Placement::First => 0,
+        Placement::Last => keys.len(),
+        Placement::Before(sibling) => position(sibling)?,
+        Placement::After(sibling) => position(sibling)? + 1,
+    };
+    keys.insert(index, name.to_string());
+    Ok(keys)
+}
+
+/// Renumbers the tables of the document so that those below `name` (a key of
+/// the table at `parent`) are written at `placement` among those below its
+/// siblings. The tables starting a new run take the blank lines found between
+/// the others.
+fn move_tables(edit: &mut DocumentMut, parent: &[Segment], name: &str, placement: &Placement) {
+    // Every table with a header, and the key of the parent it is found under.
+    let mut tables = Vec::new();
+    collect_tables(edit.as_table(), Some(parent), None, &mut tables);
+
+    // The order they are written in: tables without a position follow the
+    // one before them.
+    let mut last = 0;
+    let mut order: Vec<(usize, usize, Option<&str>)> = tables.iter()
+        .enumerate()
+        .map(|(i, table)| {
+            last = table.position.unwrap_or(last);
+            (last, i, table.branch)
+        })
+        .collect();
+    order.sort_by_key(|(position, i, _)| (*position, *i));
+    // The table at the top of the file, which has no blank lines above it.
+    let first = order.iter().map(|(_, i, _)| *i).find(|&i| tables[i].position.is_some());
+
+    let (moved, rest): (Vec<_>, Vec<_>) = order.into_iter().partition(|(_, _, branch)| *branch == Some(name));
+    // Where the tables below all siblings, or below one of them, are written.
+    let indices = |sibling: Option<&str>| -> Vec<usize> {
+        rest.iter()
+            .enumerate()
+            .filter(|(_, (_, _, branch))| branch.is_some() && (sibling.is_none() || *branch == sibling))
+            .map(|(i, _)| i)
+            .collect()
+    };
+    let index = match placement {
+        Placement::First => indices(None).first().copied(),
+        Placement::Last => indices(None).last().map(|i| i + 1),
+        Placement::Before(sibling) => indices(Some(sibling)).first().copied(),
+        Placement::After(sibling) => indices(Some(sibling)).last().map(|i| i + 1),
+    };
+    let Some(index) = index.filter(|_| !moved.is_empty()) else {
+        return;
+    };
+
+    let written: Vec<usize> = rest[..index].iter().chain(&moved).chain(&rest[index..]).map(|(_, i, _)| *i).collect();
+    // The blank lines above the existing tables nearest the new place.
+    let gap = rest[index..].iter().chain(rest[..index].iter().rev())
+        .map(|(_, i, _)| &tables[*i])
+        .find(|table| table.position.is_some() && Some(table.index) != first)
+        .map_or("\n", |table| table.gap)
+        .to_string();
+    let root_values = edit.as_table().iter().any(|(_, item)| item.is_value());
+
+    let mut updates = vec![(0, None); tables.len()];
+    for (position, &i) in written.iter().enumerate() {
+        let gap = if position == 0 {
+            // Only after the keys at the top of the file.
+            (Some(i) != first).then(|| if root_values { gap.clone() } else { String::new() })
+        } else {
+            (position == index || Some(i) == first).then(|| gap.clone())
+        };
+        updates[i] = (position + 1, gap);
+    }
+    assign_positions(edit.as_table_mut(), &mut updates.into_iter());
+}
+
+/// A table with a header, as seen by `move_tables`.
+struct TableEntry<'a> {
+    /// In the order `collect_tables` visits the tables.
+    index: usize,
+    position: Option<usize>,
+    /// The key of the parent table it is found under.
+    branch: Option<&'a str>,
+    /// The blank lines above its header.
+    gap: &'a str,
+}
+
+impl<'a> TableEntry<'a> {
+    fn new(table: &'a Table, index: usize, branch: Option<&'a str>) -> Self {
+        let gap = split_blank(table.decor().prefix().and_then(|raw| raw.as_str()).unwrap_or("\n")).0;
+        TableEntry { index, position: table.position(), branch, gap }
+    }
+}
+
+/// Collects the tables below `table`; `parent` is what is left of the path to
+/// the parent table, if this is on the way to it.
+fn collect_tables<'a>(table: &'a Table, parent: Option<&[Segment]>, branch: Option<&'a str>, tables: &mut Vec<TableEntry<'a>>) {
+    for (key, item) in table.iter() {
+        let branch = if parent.is_some_and(|parent| parent.is_empty()) { Some(key) } else { branch };
+        let parent = match parent.and_then(|parent| parent.split_first()) {
+            Some((Segment::Key(name), rest)) if *name == key => Some(rest),
+            _ => None,
+        };
+        match item {
+            Item::Table(table) => {
+                if !table.is_dotted() {
+                    tables.push(TableEntry::new(table, tables.len(), branch));
+                }
+                collect_tables(table, parent, branch, tables);
+            },
+            Item::ArrayOfTables(array) => {
+                for (i, table) in array.iter().enumerate() {
+                    tables.push(TableEntry::new(table, tables.len(), branch));
+                    let parent = match parent.and_then(|parent| parent.split_first()) {
+                        Some((Segment::Index(index), rest)) if index.unwrap_or(array.len() - 1) == i => Some(rest),
+                        _ => None,
+                    };
+                    collect_tables(table, parent, branch, tables);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+fn update_table(table: &mut Table, (position, gap): (usize, Option<String>)) {
+    table.set_position(position);
+    if let Some(gap) = gap {
+        let prefix = table.decor().prefix().and_then(|raw| raw.as_str()).unwrap_or("").to_string();
+        table.decor_mut().set_prefix(format!("{}{}", gap, split_blank(&prefix).1));
+    }
+}
+
+/// Sets the positions of the tables visited by `collect_tables`, in order,
+/// and the blank lines above those that get new ones.
+fn assign_positions(table: &mut Table, updates: &mut impl Iterator<Item = (usize, Option<String>)>) {
+    for (_, item) in table.iter_mut() {
+        match item {
+            Item::Table(table) => {
+                if !table.is_dotted() {
+                    update_table(table, updates.next().unwrap());
+                }
+                assign_positions(table, updates);
+            },
+            Item::ArrayOfTables(array) => {
+                for table in array.iter_mut() {
+                    update_table(table, updates.next().unwrap());
+                    assign_positions(table, updates);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+fn sync_table(table: &mut Table, value: &toml::Table, style: Option<TableStyle>) {
+    table.retain(|key, _| value.contains_key(key));
+    for (key, new) in value {
+        match table.get_mut(key) {
+            Some(item) => {
+                let was_value = item.is_value();
+                let value_suffix = item.as_value().and_then(|value| value.decor().suffix()).and_then(|raw| raw.as_str()).map(str::to_string);
+                sync_item(item, new, style);
+                // The spacing of `key = ` does not fit a `[key]` header, nor
+                // the other way round.
+                if item.is_value() != was_value {
+                    let position = table.position();
+                    let mut key_decor = table.key_mut(key).unwrap();
+                    let key_prefix = key_decor.leaf_decor().prefix().and_then(|raw| raw.as_str()).map(str::to_string);
+                    key_decor.leaf_decor_mut().clear();
+                    if was_value {
+                        let item = table.get_mut(key).unwrap();
+                        keep_statement_decor(item, key_prefix.as_deref(), value_suffix.as_deref(), position);
+                    }
+                }
+            },
+            None => {
+                table.insert(key, new_item(new, style));
+            },
+        }
+    }
+}
+
+fn sync_item(item: &mut Item, value: &Value, style: Option<TableStyle>) {
+    match (item, value) {
+        (Item::Table(table), Value::Table(new)) => sync_table(table, new, style),
+        (Item::ArrayOfTables(array), Value::Array(new)) if is_array_of_tables(new) => {
+            sync_array_of_tables(array, new, style)
+        },
+        (item @ Item::Value(toml_edit::Value::Array(_)), Value::Array(new))
+            if style == Some(TableStyle::Header) && is_array_of_tables(new)
+                && item.as_array().is_some_and(|array| array.len() < new.len()) =>
+        {
+            *item = new_item(value, style)
+        },
+        (Item::Value(current), Value::Table(_)) if current.is_inline_table() => sync_value(current, value),
+        (Item::Value(current), Value::Array(_)) if current.is_array() => sync_value(current, value),
+        (Item::Value(current), _) if !value.is_table() && !is_array_of_tables_value(value) => {
+            sync_value(current, value)
+        },
+        (item, _) => *item = new_item(value, style),
+    }
+}
+
+/// Moves the comments around a `key = value` statement turned into `[key]` or
+/// `[[key]]` tables onto the first header, and writes the tables right after
+/// their parent's keys, where the statement was.
+fn keep_statement_decor(item: &mut Item, key_prefix: Option<&str>, value_suffix: Option<&str>, position: Option<usize>) {
+    let mut tables: Vec<&mut Table> = match item {
+        Item::Table(table) => vec![table],
+        Item::ArrayOfTables(array) => array.iter_mut().collect(),
+        _ => return,
+    };
+    if let Some(position) = position {
+        for table in tables.iter_mut() {
+            table.set_position(position);
+        }
+    }
+    let Some(first) = tables.first_mut() else {
+        return;
+    };
+    if let Some(prefix) = key_prefix.filter(|prefix| prefix.contains('#')) {
+        first.set_implicit(false);
+        first.decor_mut().set_prefix(format!("\n{}", prefix.trim_start_matches('\n').trim_end_matches([' ', '\t'])));
+    }
+    if let Some(suffix) = value_suffix.filter(|suffix| suffix.contains('#')) {
+        first.set_implicit(false);
+        first.decor_mut().set_suffix(suffix.to_string());
+    }
+}
+
+fn sync_array_of_tables(array: &mut ArrayOfTables, value: &[Value], style: Option<TableStyle>) {
+    while array.len() > value.len() {
+        array.remove(array.len() - 1);
+    }
+    for (i, new) in value.iter().enumerate() {
+        let new = new.as_table().unwrap();
+        match array.get_mut(i) {
+            Some(table) => sync_table(table, new, style),
+            None => array.push(new_table(new, style)),
+        }
+    }
+}
+
+fn sync_value(current: &mut toml_edit::Value, value: &Value) {
+    if scalar_eq(current, value) {
+        return;
+    }
+    match (&mut *current, value) {
+        (toml_edit::Value::InlineTable(table), Value::Table(new)) => sync_inline_table(table, new),
+        (toml_edit::Value::Array(array), Value::Array(new)) => sync_array(array, new),
+        _ => {
+            let mut replacement = new_value(value);
+            if let (toml_edit::Value::Integer(old), Value::Integer(new)) = (&*current, value) {
+                if let Some(literal) = same_notation(old, *new) {
+                    replacement = literal;
+                }
+            }
+            *replacement.decor_mut() = current.decor().clone();
+            *current = replacement;
+        },
+    }
+}
+
+fn sync_inline_table(table: &mut InlineTable, value: &toml::Table) {
+    table.retain(|key, _| value.contains_key(key));
+    for (key, new) in value {
+        match table.get_mut(key) {
+            Some(current) => sync_value(current, new),
+            None => {
+                if let Some((_, last)) = table.iter_mut().last() {
+                    let suffix = last.decor().suffix().cloned().unwrap_or_default();
+                    last.decor_mut().set_suffix("");
+                    let mut value = new_value(new);
+                    value.decor_mut().set_suffix(suffix);
+                    table.insert(key, value);
+                } else {
+                    table.insert(key, new_value(new));
+                }
+            },
+        }
+    }
+}
+
+fn sync_array(array: &mut Array, value: &[Value]) {
+    while array.len() > value.len() {
+        array.remove(array.len() - 1);
+    }
+    for (i, new) in value.iter().enumerate() {
+        match array.get_mut(i) {
+            Some(current) => sync_value(current, new),
+            None => push_value(array, new_value(new)),
+        }
+    }
+}
+
+/// Appends to an array in the style of its existing elements, so that
+/// `[ 1, 2 ]` becomes `[ 1, 2, 3 ]` and multi-line arrays stay one per line.
+fn push_value(array: &mut Array, mut value: toml_edit::Value) {
+    let len = array.len();
+    let Some(last) = array.get_mut(len.wrapping_sub(1)) else {
+        array.push(value);
+        return;
+    };
+    let mut prefix = match last.decor().prefix().and_then(|prefix| prefix.as_str()) {
+        // Only the line break and indentation: the comments above the last
+        // element, or after the one before it, are theirs.
+        Some(prefix) if prefix.contains('\n') => format!("\n{}", &prefix[prefix.rfind('\n').unwrap() + 1..]),
+        Some(prefix) if len > 1 => prefix.to_string(),
+        _ => " ".to_string(),
+    };
+    let suffix = last.decor().suffix().cloned().unwrap_or_default();
+    last.decor_mut().set_suffix("");
+    // The comment after the last element stays on its line.
+    let trailing = array.trailing().as_str().unwrap_or("").to_string();
+    if let Some(end) = trailing.find('\n').filter(|_| prefix.starts_with('\n')) {
+        prefix.insert_str(0, &trailing[..end]);
+        array.set_trailing(&trailing[end..]);
+    }
+    value.decor_mut().set_prefix(prefix);
+    value.decor_mut().set_suffix(suffix);
+    array.push_formatted(value);
+}
+
+/// Formats `value` like `old` when `old` is written in hex, octal or binary.
+fn same_notation(old: &toml_edit::Formatted<i64>, value: i64) -> Option<toml_edit::Value> {
+    let repr = old.as_repr()?.as_raw().as_str()?;
+    if value < 0 {
+        return None;
+    }
+    let literal = match &repr[..repr.len().min(2)] {
+        "0x" if repr[2..].chars().any(|c| c.is_ascii_lowercase()) => format!("0x{:x}", value),
+        "0x" => format!("0x{:X}", value),
+        "0o" => format!("0o{:o}", value),
+        "0b" => format!("0b{:b}", value),
+        _ => return None,
+    };
+    literal.parse().ok()
+}
+
+/// Writes a string with as few escapes as possible: as a `'literal'` when it
+/// holds quotes or backslashes, and as a multi-line string when it holds
+/// newlines. Basic strings are the fallback for anything a literal cannot hold.
+fn new_string(s: &str) -> toml_edit::Value {
+    let control = |c: char| c.is_control() && c != '\n' && c != '\t';
+    let literal = if s.contains('\n') {
+        if !s.contains("'''") && !s.chars().any(control) {
+            format!("'''\n{}'''", s)
+        } else {
+            format!("\"\"\"\n{}\"\"\"", escape(s))
+        }
+    } else if s.contains(['"', '\\']) && !s.contains('\'') && !s.chars().any(control) {
+        format!("'{}'", s)
+    } else {
+        format!("\"{}\"", escape(s))
+    };
+    literal.parse().expect("a valid string literal")
+}
+
+/// The body of a basic string. Only multi-line strings contain newlines, and
+/// they keep them as they are.
+fn escape(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' | '\t' => escaped.push(c),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn scalar_eq(current: &toml_edit::Value, value: &Value) -> bool {
+    match (current, value) {
+        (toml_edit::Value::String(s), Value::String(v)) => s.value() == v,
+        (toml_edit::Value::Integer(i), Value::Integer(v)) => i.value() == v,
+        (toml_edit::Value::Float(f), Value::Float(v)) => f.value().to_bits() == v.to_bits(),
+        (toml_edit::Value::Boolean(b), Value::Boolean(v)) => b.value() == v,
+        (toml_edit::Value::Datetime(d), Value::Datetime(v)) => d.value() == v,
+        _ => false,
+    }
+}
+
+fn is_array_of_tables(array: &[Value]) -> bool {
+    !array.is_empty() && array.iter().all(Value::is_table)
+}
+
+fn is_array_of_tables_value(value: &Value) -> bool {
+    value.as_array().is_some_and(|array| is_array_of_tables(array))
+}
+
+fn new_item(value: &Value, style: Option<TableStyle>) -> Item {
+    match (value, style) {
+        // An empty table has no keys to write with dots, so it is written `{}`.
+        (Value::Table(table), Some(TableStyle::Dotted)) if !table.is_empty() => {
+            let mut table = new_table(table, style);
+            table.set_dotted(true);
+            Item::Table(table)
+        },
+        (Value::Table(_), Some(TableStyle::Inline | TableStyle::Dotted)) | (Value::Array(_), Some(TableStyle::Inline)) => {
+            Item::Value(new_value(value))
+        },
+        (Value::Table(table), _) => Item::Table(new_table(table, style)),
+        (Value::Array(array), _) if is_array_of_tables(array) => {
+            Item::ArrayOfTables(array.iter().filter_map(Value::as_table).map(|table| new_table(table, style)).collect())
+        },
+        _ => Item::Value(new_value(value)),
+    }
+}
+
+fn new_table(value: &toml::Table, style: Option<TableStyle>) -> Table {
+    let mut table = Table::new();
+    table.set_implicit(true);
+    for (key, new) in value {
+        table.insert(key, new_item(new, style));
+    }
+    table
+}
+
+fn new_value(value: &Value) -> toml_edit::Value {
+    match value {
+        Value::String(s) => new_string(s),
+        Value::Integer(i) => (*i).into(),
+        Value::Float(f) => (*f).into(),
+        Value::Boolean(b) => (*b).into(),
+        Value::Datetime(dt) => (*dt).into(),
+        Value::Array(array) => toml_edit::Value::Array(array.iter().map(new_value).collect()),
+        Value::Table(table) => toml_edit::Value::InlineTable(
+            table.iter().map(|(key, value)| (key.clone(), new_value(value))).collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::{copy_value, insert_value, move_value, remove_value, set_value};
+
+    fn apply(content: &str, edit: impl FnOnce(&mut Value)) -> String {
+        let mut edit_document: DocumentMut = content.parse().unwrap();
+        let mut value = toml::from_str(content).unwrap();
+        edit(&mut value);
+        sync(&mut edit_document, &value);
+        edit_document.to_string()
+    }
+
+    #[test]
+    fn test_mark_conflicts() {
+        let conflicts = vec![Conflict {
+            path: "package.version".to_string(),
+            ours: Some(Value::String("0.2.0".to_string())),
+            theirs: None,
+        }];
+        let edit: DocumentMut = format!("[package]\nname = \"ctoml\"\nversion = \"{}\" # bump\n", conflict_marker(0)).parse().unwrap();
+
+        assert_eq!(mark_conflicts(&edit, &conflicts), [
+            "[package]",
+            "name = \"ctoml\"",
+            "<<<<<<< ours",
+            "version = \"0.2.0\" # bump",
+            "=======",
+            ">>>>>>> theirs",
+            "",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn test_mark_conflicts_in_inline_tables() {
+        let conflicts = vec![
+            Conflict { path: "a.b".to_string(), ours: Some(Value::Integer(2)), theirs: Some(Value::Integer(3)) },
+            Conflict { path: "a.c".to_string(), ours: None, theirs: Some(Value::Integer(4)) },
+        ];
+        let text = format!(
+            "x = 1\na = {{ b = \"{}\", c = \"{}\", d = [\n  1,\n] }} # pair\ny = 2\n",
+            conflict_marker(0),
+            conflict_marker(1),
+        );
+        let marked = mark_conflicts(&text.parse().unwrap(), &conflicts);
+
+        assert_eq!(marked, [
+            "x = 1",
+            "<<<<<<< ours",
+            "a = { b = 2, d = [",
+            "  1,",
+            "] } # pair",
+            "=======",
+            "a = { b = 3, c = 4, d = [",
+            "  1,",
+            "] } # pair",
+            ">>>>>>> theirs",
+            "y = 2",
+            "",
+        ].join("\n"));
+        // Keeping either side must leave valid TOML.
+        for side in ["ours", "theirs"] {
+            let (mut kept, mut section) = (String::new(), "");
+            for line in marked.split_inclusive('\n') {
+                match line.trim_end() {
+                    "<<<<<<< ours" => section = "ours",
+                    "=======" => section = "theirs",
+                    ">>>>>>> theirs" => section = "",
+                    _ if section.is_empty() || section == side => kept.push_str(line),
+                    _ => {},
+                }
+            }
+            assert!(kept.parse::<DocumentMut>().is_ok(), "{}", kept);
+        }
+    }
+
+    #[test]
+    fn test_sync_preserves_untouched_formatting() {
+        let content = "# settings\nmode = 0o755 # rwx\nmask = 0xFF\nbig = 1_000_000\n\n[server]\nport = 8080\n";
+        let result = apply(content, |value| set_value(value, "server.port", "9090").unwrap());
+        assert_eq!(result, "# settings\nmode = 0o755 # rwx\nmask = 0xFF\nbig = 1_000_000\n\n[server]\nport = 9090\n");
+    }
+
+    #[test]
+    fn test_sync_keeps_notation_of_changed_integers() {
+        let content = "mode = 0o644\nmask = 0xff\nflags = 0b0011\n";
+        let result = apply(content, |value| {
+            set_value(value, "mode", "493").unwrap();
+            set_value(value, "mask", "0x1F").unwrap();
+            set_value(value, "flags", "5").unwrap();
+        });
+        assert_eq!(result, "mode = 0o755\nmask = 0x1f\nflags = 0b101\n");
+    }
+
+    #[test]
+    fn test_sync_writes_strings_with_few_escapes() {
+        let result = apply("", |value| {
+            set_value(value, "plain", "hello").unwrap();
+            set_value(value, "quoted", r#"'say "hi"'"#).unwrap();
+            set_value(value, "path", r"'C:\Users'").unwrap();
+            set_value(value, "motd", r#""Welcome!\nHave a nice day""#).unwrap();
+            set_value(value, "both", r#""it's \"ok\"""#).unwrap();
+            set_value(value, "code", r#""a '''\nb\\""#).unwrap();
+        });
+        // New keys of a new document come in alphabetical order.
+        assert_eq!(result, [
+            r#"both = "it's \"ok\"""#,
+            r#"code = """"#,
+            "a '''",
+            r#"b\\""""#,
+            "motd = '''",
+            "Welcome!",
+            "Have a nice day'''",
+            r"path = 'C:\Users'",
+            r#"plain = "hello""#,
+            r#"quoted = 'say "hi"'"#,
+            "",
+        ].join("\n"));
+        let value: Value = toml::from_str(&result).unwrap();
+        assert_eq!(value["code"].as_str(), Some("a '''\nb\\"));
+    }
+
+    #[test]
+    fn test_sync_adds_and_removes() {
+        let content = "a = 1\nb = [1, 2, 3]\n\n[[products]]\nname = \"Hammer\"\n\n[[products]]\nname = \"Nail\"\n";
+        let result = apply(content, |value| {
+            remove_value(value, "a").unwrap();
+            remove_value(value, "b[1]").unwrap();
+            remove_value(value, "products[0]").unwrap();
+            set_value(value, "deep.nested.value", "42").unwrap();
+        });
+        assert_eq!(result, "b = [1, 3]\n\n[[products]]\nname = \"Nail\"\n\n[deep.nested]\nvalue = 42\n");
+    }
+
+    #[test]
+    fn test_sync_appends_in_existing_style() {
+        let content = "a = [ 1, 2 ]\nb = [\n  \"x\",\n]\nc = { d = 1 }\n";
+        let result = apply(content, |value| {
+            set_value(value, "a[]", "3").unwrap();
+            set_value(value, "b[]", "y").unwrap();
+            set_value(value, "c.e", "2").unwrap();
+        });
+        assert_eq!(result, "a = [ 1, 2, 3 ]\nb = [\n  \"x\",\n  \"y\",\n]\nc = { d = 1, e = 2 }\n");
+    }
+
+    fn apply_inserted(content: &str, key: &str, value: &str) -> String {
+        let mut document = Document {
+            edit: content.parse().unwrap(),
+            value: toml::from_str(content).unwrap(),
+            literals: Vec::new(),
+            style: None,
+            placement: None,
+        };
+        let inserted = insert_value(&mut document.value, key, value).unwrap();
+        insert_elements(&mut document, &key[..key.rfind('[').unwrap()], inserted).unwrap();
+        sync(&mut document.edit, &document.value);
+        document.edit.to_string()
+    }
+
+    #[test]
+    fn test_insert_elements_keeps_comments_and_notation() {
+        let content = "ports = [\n  80, # http\n  0x1BB, # https\n]\n";
+        assert_eq!(apply_inserted(content, "ports[0]", "22"), "ports = [\n  22,\n  80, # http\n  0x1BB, # https\n]\n");
+        assert_eq!(apply_inserted(content, "ports[2]", "...[1, 2]"), "ports = [\n  80, # http\n  0x1BB, # https\n  1,\n  2,\n]\n");
+
+        let content = "# The hammer\n[[products]]\nname = \"Hammer\"\nsku = 0x10\n\n[[products]]\nname = \"Nail\"\n\n[x]\ny = 1\n";
+        assert_eq!(apply_inserted(content, "products[0]", "{name: \"Saw\"}"), [
+            "[[products]]\nname = \"Saw\"\n",
+            "# The hammer\n[[products]]\nname = \"Hammer\"\nsku = 0x10\n",
+            "[[products]]\nname = \"Nail\"\n",
+            "[x]\ny = 1\n",
+        ].join("\n"));
+    }
+
+    fn apply_copied(content: &str, from: &str, to: &str, moved: bool) -> String {
+        let mut document = Document {
+            edit: content.parse().unwrap(),
+            value: toml::from_str(content).unwrap(),
+            literals: Vec::new(),
+            style: None,
+            placement: None,
+        };
+        if moved {
+            move_value(&mut document.value, from, to, false).unwrap();
+        } else {
+            copy_value(&mut document.value, from, to, false).unwrap();
+        }
+        copy_item(&mut document, from, to);
+        sync(&mut document.edit, &document.value);
+        document.edit.to_string()
+    }
+
+    #[test]
+    fn test_copy_item_keeps_comments_and_notation() {
+        let content = "# file mode\nmode = 0o755 # rwx\nports = [80, 0x1BB]\n";
+        assert_eq!(apply_copied(content, "mode", "perms", true), "ports = [80, 0x1BB]\n# file mode\nperms = 0o755 # rwx\n");
+        assert_eq!(apply_copied(content, "mode", "ports[]", false), "# file mode\nmode = 0o755 # rwx\nports = [80, 0x1BB, 0o755]\n");
+        assert_eq!(apply_copied(content, "ports[1]", "server.port", true), "# file mode\nmode = 0o755 # rwx\nports = [80]\n\n[server]\nport = 0x1BB\n");
+
+        let content = "# The server\n[server]\nport = 0x50 # http\nhost = \"h\"\n";
+        assert_eq!(apply_copied(content, "server", "app.server", true), "# The server\n[app.server]\nport = 0x50 # http\nhost = \"h\"\n");
+    }
+
+    fn apply_styled(content: &str, style: TableStyle, edit: impl FnOnce(&mut Value)) -> String {
+        let mut edit_document: DocumentMut = content.parse().unwrap();
+        let mut value = toml::from_str(content).unwrap();
+        edit(&mut value);
+        sync_styled(&mut edit_document, &value, Some(style));
+        edit_document.to_string()
+    }
+
+    #[test]
+    fn test_sync_table_styles() {
+        let content = "[server]\nport = 80\n";
+        let set = |value: &mut Value| {
+            set_value(value, "server.tls.cert", "a.pem").unwrap();
+            set_value(value, "log.level", "info").unwrap();
+        };
+
+        assert_eq!(apply_styled(content, TableStyle::Inline, set), "log = { level = \"info\" }\n[server]\nport = 80\ntls = { cert = \"a.pem\" }\n");
+        assert_eq!(apply_styled(content, TableStyle::Dotted, set), "log.level = \"info\"\n[server]\nport = 80\ntls.cert = \"a.pem\"\n");
+        assert_eq!(apply_styled(content, TableStyle::Header, set), "[server]\nport = 80\n\n[server.tls]\ncert = \"a.pem\"\n\n[log]\nlevel = \"info\"\n");
+    }
+
+    #[test]
+    fn test_sync_arrays_of_tables_styles() {
+        let content = "products = [{ name = \"Hammer\" }]\n";
+        let append = |value: &mut Value| set_value(value, "products[]", "{name: \"Nail\"}").unwrap();
+
+        assert_eq!(apply(content, append), "products = [{ name = \"Hammer\" }, { name = \"Nail\" }]\n");
+        assert_eq!(apply_styled(content, TableStyle::Header, append), "[[products]]\nname = \"Hammer\"\n\n[[products]]\nname = \"Nail\"\n");
+        assert_eq!(apply_styled("", TableStyle::Inline, append), "products = [{ name = \"Nail\" }]\n");
+
+        // The comments stay with the entries, which go where the array was.
+        let content = "[shop.a]\nx = 1\n\n[shop]\n# Sold here\nproducts = [{ name = \"Hammer\" }] # list\nowner = \"me\"\n\n[server]\nport = 80\n";
+        let append = |value: &mut Value| set_value(value, "shop.products[]", "{name: \"Nail\"}").unwrap();
+        assert_eq!(apply_styled(content, TableStyle::Header, append), [
+            "[shop.a]\nx = 1\n",
+            "[shop]\nowner = \"me\"\n",
+            "# Sold here\n[[shop.products]] # list\nname = \"Hammer\"\n",
+            "[[shop.products]]\nname = \"Nail\"\n",
+            "[server]\nport = 80\n",
+        ].join("\n"));
+    }
+
+    fn apply_placed(content: &str, key: &str, value: &str, placement: Placement) -> Result<String, Box<dyn Error>> {
+        let mut document = Document {
+            edit: content.parse().unwrap(),
+            value: toml::from_str(content).unwrap(),
+            literals: Vec::new(),
+            style: None,
+            placement: Some((key.to_string(), placement)),
+        };
+        let path = tempfile::NamedTempFile::new().unwrap();
+        set_value(&mut document.value, key, value).unwrap();
+        save(path.path().to_str().unwrap(), &mut document)?;
+        Ok(fs::read_to_string(path.path()).unwrap())
+    }
+
+    #[test]
+    fn test_place_keys() {
+        let content = "[package]\nname = \"ctoml\"\nversion = \"0.1.0\"\nedition = \"2021\"\nkeywords = { a = 1, b = 2 }\n";
+
+        let placed = apply_placed(content, "package.description", "CLI", Placement::After("version".to_string())).unwrap();
+        assert_eq!(placed, content.replace("edition", "description = \"CLI\"\nedition"));
+        let placed = apply_placed(content, "package.license", "MIT", Placement::First).unwrap();
+        assert_eq!(placed, "[package]\nlicense = \"MIT\"\nname = \"ctoml\"\nversion = \"0.1.0\"\nedition = \"2021\"\nkeywords = { a = 1, b = 2 }\n");
+        let placed = apply_placed(content, "package.keywords.c", "3", Placement::Before("a".to_string())).unwrap();
+        assert!(placed.ends_with("keywords = { c = 3, a = 1, b = 2 }\n"));
+    }
+
+    #[test]
+    fn test_place_tables() {
+        let content = "top = 1\n\n[a]\nx = 1\n\n[a.sub]\ny = 2\n\n[c]\nz = 3\n";
+
+        let placed = apply_placed(content, "b.value", "2", Placement::After("a".to_string())).unwrap();
+        assert_eq!(placed, "top = 1\n\n[a]\nx = 1\n\n[a.sub]\ny = 2\n\n[b]\nvalue = 2\n\n[c]\nz = 3\n");
+        let placed = apply_placed(content, "b.value", "2", Placement::First).unwrap();
+        assert_eq!(placed, "top = 1\n\n[b]\nvalue = 2\n\n[a]\nx = 1\n\n[a.sub]\ny = 2\n\n[c]\nz = 3\n");
+        let placed = apply_placed(content, "b.sub.value", "2", Placement::Before("c".to_string())).unwrap();
+        assert_eq!(placed, "top = 1\n\n[a]\nx = 1\n\n[a.sub]\ny = 2\n\n[b.sub]\nvalue = 2\n\n[c]\nz = 3\n");
+
+        // New tables are spaced like the ones around them.
+        let content = "[a]\nx = 1\n[a.t]\ny = 2\n[a.u]\nz = 3\n";
+        let placed = apply_placed(content, "a.v.w", "1", Placement::Before("t".to_string())).unwrap();
+        assert_eq!(placed, "[a]\nx = 1\n[a.v]\nw = 1\n[a.t]\ny = 2\n[a.u]\nz = 3\n");
+        let content = "# head\n[a]\nx = 1\n\n[b]\ny = 2\n";
+        let placed = apply_placed(content, "c.w", "1", Placement::First).unwrap();
+        assert_eq!(placed, "[c]\nw = 1\n\n# head\n[a]\nx = 1\n\n[b]\ny = 2\n");
+    }
+
+    #[test]
+    fn test_place_existing_keys() {
+        // Existing keys stay where they are, whatever the placement.
+        let content = "[a]\nx = 1\ny = 2\nz = 3\n\n[b]\nw = 4\n";
+        let placed = apply_placed(content, "a.x", "5", Placement::Last).unwrap();
+        assert_eq!(placed, content.replace("x = 1", "x = 5"));
+        let placed = apply_placed(content, "a.z", "5", Placement::Before("x".to_string())).unwrap();
+        assert_eq!(placed, content.replace("z = 3", "z = 5"));
+        let placed = apply_placed(content, "b", "{w: 6}", Placement::First).unwrap();
+        assert_eq!(placed, content.replace("w = 4", "w = 6"));
+        let placed = apply_placed(content, "a.y", "5", Placement::After("nope".to_string())).unwrap();
+        assert_eq!(placed, content.replace("y = 2", "y = 5"));
+    }
+
+    #[test]
+    fn test_place_errors() {
+        let content = "[a]\nx = 1\n";
+
+        assert!(apply_placed(content, "a.y", "2", Placement::After("z".to_string())).is_err());
+        assert!(apply_placed(content, "a.y", "2", Placement::Before("a".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_keep_integer_literal() {
+        let mut document: DocumentMut = "mode = 493\nports = [1, 2]\n".parse().unwrap();
+        keep_integer_literal(&mut document, "mode", "0o755");
+        keep_integer_literal(&mut document, "ports[]", "0x2");
+        keep_integer_literal(&mut document, "ports[0]", "0x5");
+        assert_eq!(document.to_string(), "mode = 0o755\nports = [1, 0x2]\n");
+    }
+}
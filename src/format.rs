@@ -0,0 +1,959 @@
+//! `ctoml fmt`: lays out a whole document again — spacing, blank lines,
+//! indentation, array wrapping and table style — keeping its values, their
+//! notation and its comments. `ctoml sort-keys` only reorders keys, and
+//! `ctoml restyle` only changes how the tables of a subtree are written.
+//! `rearrange` moves the elements of an array for the commands that reorder
+//! them.
+
+use std::cmp::Ordering;
+use std::error::Error;
+use toml_edit::{Array, ArrayOfTables, Decor, DocumentMut, InlineTable, Item, KeyMut, Table, Value};
+
+use crate::document::{node_at, node_mut, NodeMut, TableStyle};
+use crate::operations::{parse_path, Segment};
+
+pub struct FormatOptions {
+    /// Sort the keys of every table, and the `[header]` tables with them.
+    pub sort_keys: bool,
+    /// Spaces to indent a `[header]` table and its keys by, per level of nesting.
+    pub indent: usize,
+    /// Write arrays that would make their line longer than this one element per
+    /// line; without it, arrays keep being written on one or several lines.
+    pub width: Option<usize>,
+    /// End arrays written one element per line with a comma.
+    pub trailing_comma: bool,
+    /// Write tables of at most this many plain values as inline tables, and
+    /// larger inline tables as `[header]` tables.
+    pub inline_max: Option<usize>,
+    /// Blank lines before each `[header]` table.
+    pub blank_lines: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions { sort_keys: false, indent: 0, width: None, trailing_comma: false, inline_max: None, blank_lines: 1 }
+    }
+}
+
+/// Reformats `document` and returns its new text.
+pub fn format(document: &mut DocumentMut, options: &FormatOptions) -> String {
+    if let Some(max) = options.inline_max {
+        restyle_tables(document.as_table_mut(), max);
+    }
+    if options.sort_keys {
+        let order = KeyOrder { recursive: true, ..Default::default() };
+        sort_keys(document, "", &order).expect("the document is a table");
+    }
+
+    format_body(document.as_table_mut(), "", 0, &mut true, options);
+    format_headers(document.as_table_mut(), 0, options);
+    // Comments after the last key or table.
+    let trailing = document.trailing().as_str().unwrap_or("").to_string();
+    let blank_lines = usize::from(has_blank_line(&trailing) && !comments(&trailing).is_empty());
+    document.set_trailing(line_prefix(&trailing, blank_lines, ""));
+
+    // The first table of a document without top-level keys is not preceded by
+    // blank lines.
+    let text = document.to_string();
+    text.trim_start_matches('\n').to_string()
+}
+
+#[derive(Default)]
+pub struct KeyOrder {
+    /// Keys to put first, in this order; the others follow alphabetically.
+    pub priority: Vec<String>,
+    /// Sort the tables below as well.
+    pub recursive: bool,
+}
+
+/// Sorts the keys of the table at `key` (the whole document when empty). Its
+/// `[header]` sub-tables are moved with their keys, while `[[array of tables]]`
+/// entries keep their place in the file.
+pub fn sort_keys(document: &mut DocumentMut, key: &str, order: &KeyOrder) -> Result<(), Box<dyn Error>> {
+    let node = if key.is_empty() {
+        NodeMut::Table(document.as_table_mut())
+    } else {
+        node_mut(document, key).ok_or_else(|| format!("{} does not exist", key))?
+    };
+    match node {
+        NodeMut::Table(table) | NodeMut::Item(Item::Table(table)) => {
+            sort_table(table, order);
+            renumber(table);
+        },
+        NodeMut::Item(Item::ArrayOfTables(array)) => {
+            for table in array.iter_mut() {
+                sort_table(table, order);
+                renumber(table);
+            }
+        },
+        NodeMut::Value(Value::InlineTable(table)) => sort_inline_table(table, order),
+        _ => return Err(format!("{} is not a table", key).into()),
+    }
+    Ok(())
+}
+
+fn compare_keys(order: &KeyOrder, a: &str, b: &str) -> Ordering {
+    let rank = |key: &str| order.priority.iter().position(|first| first == key).unwrap_or(order.priority.len());
+    rank(a).cmp(&rank(b)).then_with(|| a.cmp(b))
+}
+
+fn sort_table(table: &mut Table, order: &KeyOrder) {
+    // Blank lines between keys separate groups at fixed places: they stay
+    // where they were while the keys and their comments move.
+    let blanks: Vec<String> = table.iter()
+        .filter(|(_, item)| item.is_value())
+        .map(|(key, _)| split_blank(&raw(table.key(key).unwrap().leaf_decor().prefix())).0.to_string())
+        .collect();
+    table.sort_values_by(|a, _, b, _| compare_keys(order, a.get(), b.get()));
+    let values = table.iter_mut().filter(|(_, item)| item.is_value());
+    for ((mut key, _), blank) in values.zip(blanks) {
+        let prefix = raw(key.leaf_decor().prefix());
+        key.leaf_decor_mut().set_prefix(format!("{}{}", blank, split_blank(&prefix).1));
+    }
+
+    if !order.recursive {
+        return;
+    }
+    for (_, item) in table.iter_mut() {
+        match item {
+            Item::Table(table) => sort_table(table, order),
+            Item::ArrayOfTables(array) => {
+                for table in array.iter_mut() {
+                    sort_table(table, order);
+                    renumber(table);
+                }
+            },
+            Item::Value(value) => sort_value(value, order),
+            Item::None => {},
+        }
+    }
+}
+
+fn sort_inline_table(table: &mut InlineTable, order: &KeyOrder) {
+    let mut keys: Vec<String> = table.iter().map(|(key, _)| key.to_string()).collect();
+    keys.sort_by(|a, b| compare_keys(order, a, b));
+    reorder_inline_table(table, &keys);
+    if order.recursive {
+        table.iter_mut().for_each(|(_, value)| sort_value(value, order));
+    }
+}
+
+/// Puts the entries of `table` in the order of `keys`, which are its keys. The
+/// spacing around each entry stays in place (`{ a = 1, b = 2 }`).
+pub fn reorder_inline_table(table: &mut InlineTable, keys: &[String]) {
+    let decors: Vec<(Decor, Decor)> = table.iter()
+        .map(|(key, value)| (table.key(key).unwrap().leaf_decor().clone(), value.decor().clone()))
+        .collect();
+    for key in keys {
+        let (key, value) = table.remove_entry(key).unwrap();
+        table.insert_formatted(&key, value);
+    }
+    for ((mut key, value), (key_decor, value_decor)) in table.iter_mut().zip(decors) {
+        *key.leaf_decor_mut() = key_decor;
+        *value.decor_mut() = value_decor;
+    }
+}
+
+fn sort_value(value: &mut Value, order: &KeyOrder) {
+    match value {
+        Value::InlineTable(table) => sort_inline_table(table, order),
+        Value::Array(array) => array.iter_mut().for_each(|value| sort_value(value, order)),
+        _ => {},
+    }
+}
+
+/// Writes the `[header]` tables below `table` in the order of their keys. They
+/// take over the places in the file (and the blank lines before them) these
+/// tables had, so everything else stays where it was.
+fn renumber(table: &mut Table) {
+    let mut places = Vec::new();
+    visit_header_tables(table, &mut |table| {
+        let prefix = raw(table.decor().prefix());
+        places.push((table.position().unwrap(), split_blank(&prefix).0.to_string()));
+    });
+    places.sort_by_key(|(position, _)| *position);
+
+    let mut places = places.into_iter();
+    visit_header_tables(table, &mut |table| {
+        let (position, blank) = places.next().unwrap();
+        let prefix = raw(table.decor().prefix());
+        table.set_position(position);
+        table.decor_mut().set_prefix(format!("{}{}", blank, split_blank(&prefix).1));
+    });
+}
+
+/// Calls `visit` on the `[header]` tables below `table` that have a place in
+/// the file, in key order, skipping arrays of tables.
+fn visit_header_tables(table: &mut Table, visit: &mut impl FnMut(&mut Table)) {
+    for (_, item) in table.iter_mut() {
+        if let Item::Table(table) = item {
+            if !table.is_dotted() && table.position().is_some() {
+                visit(table);
+            }
+            visit_header_tables(table, visit);
+        }
+    }
+}
+
+/// Splits a prefix into its leading blank lines and the rest: comments and
+/// indentation.
+pub fn split_blank(prefix: &str) -> (&str, &str) {
+    let end = match prefix.find('#') {
+        Some(comment) => prefix[..comment].rfind('\n').map_or(0, |i| i + 1),
+        None => prefix.rfind('\n').map_or(0, |i| i + 1),
+    };
+    prefix.split_at(end)
+}
+
+/// Puts the elements of the array or array of tables at `key` in `order`,
+/// given as the old index of each element; those it leaves out are removed.
+/// Comments move with their element, while blank lines and indentation stay
+/// in place.
+pub fn rearrange(document: &mut DocumentMut, key: &str, order: &[usize]) -> Result<(), Box<dyn Error>> {
+    match node_mut(document, key) {
+        Some(NodeMut::Value(Value::Array(array))) => rearrange_array(array, order),
+        Some(NodeMut::Item(Item::ArrayOfTables(array))) => rearrange_tables(array, order),
+        Some(_) => return Err(format!("{} is not an array", key).into()),
+        None => return Err(format!("{} does not exist", key).into()),
+    }
+    Ok(())
+}
+
+fn rearrange_array(array: &mut Array, order: &[usize]) {
+    // The prefix of each element (and the trailing text before `]`) is split
+    // into the end of the line before it, which holds the comment following
+    // the previous element, and the lines above it.
+    let mut lines: Vec<(String, String)> = array.iter().map(|value| split_line(&raw(value.decor().prefix()))).collect();
+    lines.push(split_line(&raw(Some(array.trailing()))));
+    let suffixes: Vec<String> = array.iter().map(|value| raw(value.decor().suffix())).collect();
+
+    // The end of the line of element `i`, written where the one of the element
+    // before `slot` was.
+    let end = |i: usize, slot: usize| match (&lines[i + 1].0, &lines[slot].0) {
+        (end, _) if end.contains('#') => end.clone(),
+        (_, end) if end.contains('#') => "\n".to_string(),
+        (_, end) => end.clone(),
+    };
+    let mut values = Vec::new();
+    for (slot, &i) in order.iter().enumerate() {
+        let mut value = array.get(i).unwrap().clone();
+        let end = if slot == 0 { lines[0].0.clone() } else { end(order[slot - 1], slot) };
+        value.decor_mut().set_prefix(format!("{}{}", end, above(&lines[slot].1, &lines[i].1)));
+        value.decor_mut().set_suffix(suffixes[slot].clone());
+        values.push(value);
+    }
+    let last = lines.len() - 1;
+    let trailing = match order.last() {
+        Some(&i) => format!("{}{}", end(i, last), lines[last].1),
+        None => format!("{}{}", lines[last].0, lines[last].1),
+    };
+
+    array.clear();
+    values.into_iter().for_each(|value| array.push_formatted(value));
+    array.set_trailing(trailing);
+}
+
+/// Splits a prefix after its first line break.
+fn split_line(prefix: &str) -> (String, String) {
+    let end = prefix.find('\n').map_or(0, |i| i + 1);
+    (prefix[..end].to_string(), prefix[end..].to_string())
+}
+
+/// The lines above an element: the blank lines and indentation of the place it
+/// moves to, with the comments it had above it.
+fn above(place: &str, element: &str) -> String {
+    let comments = split_blank(element).1;
+    let comments = &comments[..comments.rfind('\n').map_or(0, |i| i + 1)];
+    let indent = &place[place.rfind('\n').map_or(0, |i| i + 1)..];
+    format!("{}{}{}", split_blank(place).0, comments, indent)
+}
+
+fn rearrange_tables(array: &mut ArrayOfTables, order: &[usize]) {
+    // The entries and the tables below them take over the places in the file
+    // of the entries before them, and each entry the blank lines before the
+    // one it replaces. New entries, not placed yet, share the place of the
+    // entry after them, or else before them.
+    let mut positions = Vec::new();
+    for table in array.iter_mut() {
+        visit_placed_tables(table, &mut |table| positions.push(table.position().unwrap()));
+    }
+    positions.sort_unstable();
+    let blanks: Vec<String> = array.iter()
+        .map(|table| table.decor().prefix().map_or("\n".to_string(), |prefix| split_blank(&raw(Some(prefix))).0.to_string()))
+        .collect();
+
+    let tables: Vec<Table> = order.iter().map(|&i| array.get(i).unwrap().clone()).collect();
+    let mut positions = positions.into_iter().peekable();
+    let mut last = None;
+    array.clear();
+    for (mut table, blank) in tables.into_iter().zip(blanks) {
+        let prefix = raw(table.decor().prefix());
+        table.decor_mut().set_prefix(format!("{}{}", blank, split_blank(&prefix).1));
+        let placed = table.position().is_some();
+        visit_placed_tables(&mut table, &mut |table| {
+            last = positions.next();
+            table.set_position(last.unwrap());
+        });
+        if let Some(position) = positions.peek().copied().or(last).filter(|_| !placed) {
+            table.set_position(position);
+        }
+        array.push(table);
+    }
+}
+
+/// Calls `visit` on `table` and every table below it that has a place in the
+/// file, arrays of tables included.
+fn visit_placed_tables(table: &mut Table, visit: &mut impl FnMut(&mut Table)) {
+    if !table.is_dotted() && table.position().is_some() {
+        visit(table);
+    }
+    for (_, item) in table.iter_mut() {
+        match item {
+            Item::Table(table) => visit_placed_tables(table, visit),
+            Item::ArrayOfTables(array) => array.iter_mut().for_each(|table| visit_placed_tables(table, visit)),
+            _ => {},
+        }
+    }
+}
+
+/// Writes the table or array of tables at `key` (every one of the document
+/// when empty) and all tables below it in `style`, leaving the data as it is.
+/// Arrays of tables cannot be written with dotted keys and keep their
+/// `[[header]]` entries.
+pub fn restyle(document: &mut DocumentMut, key: &str, style: TableStyle) -> Result<(), Box<dyn Error>> {
+    if key.is_empty() {
+        if style == TableStyle::Inline {
+            if let Some((key, _)) = document.iter().find(|(_, item)| is_table_like(item) && holds_comments(item)) {
+                return Err(format!("{} holds comments, which an inline table cannot keep", key).into());
+            }
+        }
+        restyle_children(document.as_table_mut(), style);
+        return Ok(());
+    }
+
+    let mut segments = parse_path(key);
+    let Some(Segment::Key(name)) = segments.pop() else {
+        return Err(format!("{} is an array element; restyle the whole array instead", key).into());
+    };
+    let table = match node_at(document, &segments) {
+        Some(NodeMut::Table(table) | NodeMut::Item(Item::Table(table))) => table,
+        Some(NodeMut::Value(Value::InlineTable(table))) if table.contains_key(&name) => {
+            if style == TableStyle::Inline {
+                return Ok(());
+            }
+            return Err(format!("{} is inside an inline table", key).into());
+        },
+        _ => return Err(format!("{} does not exist", key).into()),
+    };
+    let (key_mut, item) = table.get_key_value_mut(&name).ok_or_else(|| format!("{} does not exist", key))?;
+    if !is_table_like(item) {
+        return Err(format!("{} is not a table or an array of tables", key).into());
+    }
+    if style == TableStyle::Dotted && !item.is_table_like() {
+        return Err(format!("{} is an array of tables, which cannot be written with dotted keys", key).into());
+    }
+    if style == TableStyle::Inline && holds_comments(item) {
+        return Err(format!("{} holds comments, which an inline table cannot keep", key).into());
+    }
+    restyle_item(key_mut, item, style);
+    Ok(())
+}
+
+fn restyle_children(table: &mut Table, style: TableStyle) {
+    for (key, item) in table.iter_mut() {
+        if is_table_like(item) {
+            restyle_item(key, item, style);
+        }
+    }
+}
+
+fn restyle_item(mut key: KeyMut, item: &mut Item, style: TableStyle) {
+    let was_value = item.is_value();
+    match style {
+        TableStyle::Inline => item.make_value(),
+        TableStyle::Header => {
+            *item = match std::mem::take(item) {
+                Item::Value(Value::InlineTable(table)) => Item::Table(table.into_table()),
+                Item::Value(array @ Value::Array(_)) => Item::Value(array).into_array_of_tables().map_or_else(|item| item, Item::ArrayOfTables),
+                item => item,
+            };
+            match item {
+                Item::Table(table) => {
+                    table.set_dotted(false);
+                    restyle_children(table, style);
+                    // Only list the header of a table with keys of its own, or
+                    // of an empty one; its inline tables are headers by now.
+                    table.set_implicit(!table.is_empty() && !table.iter().any(|(_, item)| item.is_value()));
+                },
+                Item::ArrayOfTables(array) => array.iter_mut().for_each(|table| restyle_children(table, style)),
+                _ => {},
+            }
+        },
+        TableStyle::Dotted => {
+            // An empty table has no keys to write with dots.
+            *item = match std::mem::take(item) {
+                Item::Value(Value::InlineTable(table)) if !table.is_empty() => Item::Table(table.into_table()),
+                Item::Table(table) if table.is_empty() => Item::Value(Value::InlineTable(InlineTable::new())),
+                item => item,
+            };
+            match item {
+                Item::Table(table) => {
+                    // The comments above a header go to its first key.
+                    let header = raw(table.decor().prefix());
+                    if !table.is_dotted() && !comments(&header).is_empty() {
+                        if let Some((mut first, _)) = table.iter_mut().find(|(_, item)| item.is_value()) {
+                            let prefix = raw(first.leaf_decor().prefix());
+                            first.leaf_decor_mut().set_prefix(format!("{}{}", line_prefix(&header, 0, ""), prefix));
+                        }
+                    }
+                    table.set_dotted(true);
+                    restyle_children(table, style);
+                },
+                Item::ArrayOfTables(array) => array.iter_mut().for_each(|table| restyle_children(table, style)),
+                _ => {},
+            }
+        },
+    }
+    // The spacing of `key = ` does not fit a `[key]` header, nor the other way round.
+    if item.is_value() != was_value {
+        key.leaf_decor_mut().clear();
+    }
+}
+
+/// Whether turning `item` into an inline table would lose comments: those of
+/// its headers and of the lines of its `[header]` tables.
+fn holds_comments(item: &Item) -> bool {
+    let table_holds_comments = |table: &Table| {
+        has_comment(table.decor())
+            || table.iter().any(|(key, item)| {
+                has_comment(table.key(key).unwrap().leaf_decor())
+                    || item.as_value().is_some_and(|value| has_comment(value.decor()))
+                    || holds_comments(item)
+            })
+    };
+    match item {
+        Item::Table(table) => table_holds_comments(table),
+        Item::ArrayOfTables(array) => array.iter().any(table_holds_comments),
+        _ => false,
+    }
+}
+
+/// Tables, inline tables, arrays of tables and inline arrays of them.
+fn is_table_like(item: &Item) -> bool {
+    match item {
+        Item::Value(Value::Array(array)) => !array.is_empty() && array.iter().all(Value::is_inline_table),
+        item => item.is_table_like() || item.is_array_of_tables(),
+    }
+}
+
+/// Turns small tables into inline tables and large inline tables into
+/// `[header]` tables, see `FormatOptions::inline_max`. Dotted keys are left
+/// as they are.
+fn restyle_tables(table: &mut Table, max: usize) {
+    let dotted = table.is_dotted();
+    for (mut key, item) in table.iter_mut() {
+        match item {
+            Item::Value(Value::InlineTable(inline)) if !dotted && inline.len() > max => {
+                let mut table = std::mem::take(inline).into_table();
+                restyle_tables(&mut table, max);
+                *item = Item::Table(table);
+                key.leaf_decor_mut().clear();
+            },
+            Item::Table(table) => {
+                restyle_tables(table, max);
+                if !table.is_dotted() && table.len() <= max && can_inline(table) {
+                    let mut inline = std::mem::take(table).into_inline_table();
+                    inline.fmt();
+                    *item = Item::Value(Value::InlineTable(inline));
+                    key.leaf_decor_mut().clear();
+                }
+            },
+            Item::ArrayOfTables(array) => array.iter_mut().for_each(|table| restyle_tables(table, max)),
+            _ => {},
+        }
+    }
+}
+
+/// Whether `table` holds only values and no comments, which an inline table
+/// could not keep.
+fn can_inline(table: &Table) -> bool {
+    let comments = |decor: &Decor| [decor.prefix(), decor.suffix()]
+        .into_iter()
+        .any(|raw| raw.and_then(|raw| raw.as_str()).is_some_and(|raw| raw.contains('#')));
+    !comments(table.decor())
+        && table.iter().all(|(key, item)| {
+            item.is_value() && !comments(table.key(key).unwrap().leaf_decor()) && !comments(item.as_value().unwrap().decor())
+        })
+}
+
+/// Lays out the `key = value` lines of `table` (including those of dotted keys
+/// below it), indented by `indent`. `width` is the length of the dotted key
+/// prefix written before each key.
+fn format_body(table: &mut Table, indent: &str, width: usize, first: &mut bool, options: &FormatOptions) {
+    for (mut key, item) in table.iter_mut() {
+        match item {
+            Item::Value(value) => {
+                let prefix = raw(key.leaf_decor().prefix());
+                let blank_lines = usize::from(!*first && has_blank_line(&prefix));
+                key.leaf_decor_mut().set_prefix(line_prefix(&prefix, blank_lines, indent));
+                key.leaf_decor_mut().set_suffix(" ");
+                *first = false;
+
+                let column = indent.len() + width + key.display_repr().len() + " = ".len();
+                format_value(value, indent, column, options);
+                let comment = trailing_comment(&raw(value.decor().suffix()));
+                value.decor_mut().set_prefix(" ");
+                value.decor_mut().set_suffix(comment);
+            },
+            Item::Table(table) if table.is_dotted() => {
+                let width = width + key.display_repr().len() + 1;
+                format_body(table, indent, width, first, options);
+            },
+            _ => {},
+        }
+    }
+}
+
+/// Lays out the headers and bodies of the `[header]` tables below `table`,
+/// which sits `depth` levels below the root.
+fn format_headers(table: &mut Table, depth: usize, options: &FormatOptions) {
+    for (_, item) in table.iter_mut() {
+        match item {
+            Item::Table(table) if table.is_dotted() => format_headers(table, depth, options),
+            Item::Table(table) => {
+                format_header(table, depth, options);
+                format_headers(table, depth + 1, options);
+            },
+            Item::ArrayOfTables(array) => {
+                for table in array.iter_mut() {
+                    format_header(table, depth, options);
+                    format_headers(table, depth + 1, options);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+fn format_header(table: &mut Table, depth: usize, options: &FormatOptions) {
+    let indent = " ".repeat(depth * options.indent);
+    let prefix = raw(table.decor().prefix());
+    let comment = trailing_comment(&raw(table.decor().suffix()));
+    table.decor_mut().set_prefix(line_prefix(&prefix, options.blank_lines, &indent));
+    table.decor_mut().set_suffix(comment);
+    format_body(table, &indent, 0, &mut true, options);
+}
+
+/// Lays out arrays and inline tables; other values are left as written.
+/// `column` is where the value starts on its line.
+fn format_value(value: &mut Value, indent: &str, column: usize, options: &FormatOptions) {
+    match value {
+        Value::Array(array) => format_array(array, indent, column, options),
+        Value::InlineTable(table) => format_inline_table(table, indent, column, options),
+        _ => {},
+    }
+}
+
+fn format_array(array: &mut Array, indent: &str, column: usize, options: &FormatOptions) {
+    let comments = array.iter().any(|value| has_comment(value.decor())) || array.trailing().as_str().is_some_and(|raw| raw.contains('#'));
+    let was_multiline = array.iter().any(|value| raw(value.decor().prefix()).contains('\n'))
+        || array.trailing().as_str().is_some_and(|raw| raw.contains('\n'));
+
+    let inner = format!("{}    ", indent);
+    for value in array.iter_mut() {
+        format_value(value, &inner, inner.len(), options);
+    }
+    let single_line_width = {
+        let mut single_line = array.clone();
+        single_line.fmt();
+        single_line.decor_mut().clear();
+        single_line.to_string().len()
+    };
+    let multiline = !array.is_empty() && match options.width {
+        _ if comments => true,
+        Some(width) => column + single_line_width > width,
+        None => was_multiline,
+    };
+    if !multiline {
+        array.fmt();
+        return;
+    }
+
+    for value in array.iter_mut() {
+        let prefix = raw(value.decor().prefix());
+        value.decor_mut().set_prefix(element_prefix(&prefix, &inner));
+        value.decor_mut().set_suffix("");
+    }
+    let trailing = array.trailing().as_str().unwrap_or("").to_string();
+    array.set_trailing(element_prefix(&trailing, indent));
+    array.set_trailing_comma(options.trailing_comma);
+}
+
+fn format_inline_table(table: &mut InlineTable, indent: &str, column: usize, options: &FormatOptions) {
+    table.fmt();
+    table.set_preamble("");
+    // Each value starts at least past `{ key = `; nested arrays are only wrapped
+    // when they contain comments, which cannot happen inside an inline table.
+    for (key, value) in table.iter_mut() {
+        let column = column + key.display_repr().len() + "{  = ".len();
+        format_value(value, indent, column, options);
+        value.decor_mut().clear();
+    }
+}
+
+/// The text between the end of the previous line (or element) and a key or
+/// element: blank lines and comments, then the indentation.
+fn line_prefix(raw: &str, blank_lines: usize, indent: &str) -> String {
+    let mut prefix = "\n".repeat(blank_lines);
+    for comment in comments(raw) {
+        prefix.push_str(&format!("{}{}\n", indent, comment));
+    }
+    prefix.push_str(indent);
+    prefix
+}
+
+/// The prefix of an element of a multi-line array (or of its closing
+/// bracket): a comment following the previous element stays on its line.
+fn element_prefix(raw: &str, indent: &str) -> String {
+    let (same_line, rest) = raw.split_once('\n').unwrap_or((raw, ""));
+    let mut prefix = trailing_comment(same_line);
+    for comment in comments(rest) {
+        prefix.push_str(&format!("\n{}{}", indent, comment));
+    }
+    prefix.push('\n');
+    prefix.push_str(indent);
+    prefix
+}
+
+/// The `#` comments on their own lines within a prefix; the text after the
+/// last line break is indentation.
+fn comments(raw: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = raw.split('\n').collect();
+    lines.pop();
+    lines.into_iter().map(|line| line.trim()).filter(|line| line.starts_with('#')).collect()
+}
+
+/// A comment after a value or header, separated from it by one space.
+fn trailing_comment(raw: &str) -> String {
+    match raw.trim() {
+        comment if comment.starts_with('#') => format!(" {}", comment),
+        _ => String::new(),
+    }
+}
+
+fn has_blank_line(raw: &str) -> bool {
+    let mut lines: Vec<&str> = raw.split('\n').collect();
+    lines.pop();
+    lines.iter().any(|line| line.trim().is_empty())
+}
+
+fn has_comment(decor: &Decor) -> bool {
+    raw(decor.prefix()).contains('#') || raw(decor.suffix()).contains('#')
+}
+
+fn raw(raw: Option<&toml_edit::RawString>) -> String {
+    raw.and_then(|raw| raw.as_str()).unwrap_or("").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format_str(content: &str, options: &FormatOptions) -> String {
+        let mut document: DocumentMut = content.parse().unwrap();
+        format(&mut document, options)
+    }
+
+    #[test]
+    fn test_format_defaults() {
+        let content = [
+            "",
+            "# Settings",
+            "name='ctoml'   # the name",
+            "",
+            "",
+            "ports=[ 80,443 ]",
+            "hosts = [",
+            "  'a', # first",
+            "       'b'",
+            "]",
+            "limits={soft=1,hard=0x10}",
+            "[server]",
+            "",
+            "  host   =  'localhost'",
+            "[[products]]",
+            "name = 'Hammer'",
+            "",
+            "",
+            "",
+            "# Nails",
+            "[[products]]  # second",
+            "name = 'Nail'",
+            "",
+        ].join("\n");
+
+        assert_eq!(format_str(&content, &FormatOptions::default()), [
+            "# Settings",
+            "name = 'ctoml' # the name",
+            "",
+            "ports = [80, 443]",
+            "hosts = [",
+            "    'a', # first",
+            "    'b'",
+            "]",
+            "limits = { soft = 1, hard = 0x10 }",
+            "",
+            "[server]",
+            "host = 'localhost'",
+            "",
+            "[[products]]",
+            "name = 'Hammer'",
+            "",
+            "# Nails",
+            "[[products]] # second",
+            "name = 'Nail'",
+            "",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn test_format_is_idempotent() {
+        let content = "a = 1\nb = [1,\n2]\n\n\n[t]\nc = { d = [1, 2] }\n";
+        let options = FormatOptions { width: Some(10), trailing_comma: true, indent: 2, ..Default::default() };
+        let once = format_str(content, &options);
+        assert_eq!(format_str(&once, &options), once);
+    }
+
+    #[test]
+    fn test_format_width_and_trailing_comma() {
+        let content = "short = [1, 2]\nlong = ['alpha', 'beta', 'gamma']\nwrapped = [\n1, 2\n]\n";
+
+        let options = FormatOptions { width: Some(20), trailing_comma: true, ..Default::default() };
+        assert_eq!(format_str(content, &options), [
+            "short = [1, 2]",
+            "long = [",
+            "    'alpha',",
+            "    'beta',",
+            "    'gamma',",
+            "]",
+            "wrapped = [1, 2]",
+            "",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn test_format_sort_keys_and_indent() {
+        let content = "y = 1\nx = 2\n\n[z]\nb = 1\na = 2\n\n[a.c]\nk = 1\n\n[a]\nj = 1\n";
+
+        let options = FormatOptions { sort_keys: true, indent: 2, blank_lines: 0, ..Default::default() };
+        assert_eq!(format_str(content, &options), [
+            "x = 2",
+            "y = 1",
+            "[a]",
+            "j = 1",
+            "  [a.c]",
+            "  k = 1",
+            "[z]",
+            "a = 2",
+            "b = 1",
+            "",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn test_format_inline_max() {
+        let content = "point = { x = 1, y = 2, z = 3 }\n\n[size]\nw = 1\nh = 2\n\n[commented]\n# keep\nk = 1\n";
+
+        let options = FormatOptions { inline_max: Some(2), ..Default::default() };
+        assert_eq!(format_str(content, &options), [
+            "size = { w = 1, h = 2 }",
+            "",
+            "[point]",
+            "x = 1",
+            "y = 2",
+            "z = 3",
+            "",
+            "[commented]",
+            "# keep",
+            "k = 1",
+            "",
+        ].join("\n"));
+    }
+
+    fn sort_str(content: &str, key: &str, order: &KeyOrder) -> String {
+        let mut document: DocumentMut = content.parse().unwrap();
+        sort_keys(&mut document, key, order).unwrap();
+        document.to_string()
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let content = [
+            "[package]",
+            "version = '0.1.0'",
+            "edition = '2021'",
+            "name = 'ctoml'",
+            "",
+            "[dependencies]",
+            "toml = '0.8'",
+            "# JSON output",
+            "serde_json = '1.0'",
+            "",
+            "[[bin]]",
+            "path = 'src/main.rs'",
+            "name = 'ctoml'",
+            "",
+            "[dev-dependencies]",
+            "tempfile = '3'",
+            "",
+        ].join("\n");
+
+        let order = KeyOrder { priority: vec!["name".to_string(), "version".to_string()], recursive: true };
+        assert_eq!(sort_str(&content, "", &order), [
+            "[dependencies]",
+            "# JSON output",
+            "serde_json = '1.0'",
+            "toml = '0.8'",
+            "",
+            "[dev-dependencies]",
+            "tempfile = '3'",
+            "",
+            "[[bin]]",
+            "name = 'ctoml'",
+            "path = 'src/main.rs'",
+            "",
+            "[package]",
+            "name = 'ctoml'",
+            "version = '0.1.0'",
+            "edition = '2021'",
+            "",
+        ].join("\n"));
+
+        let order = KeyOrder::default();
+        assert_eq!(sort_str(&content, "dependencies", &order), content.replace("toml = '0.8'\n# JSON output\nserde_json = '1.0'", "# JSON output\nserde_json = '1.0'\ntoml = '0.8'"));
+    }
+
+    #[test]
+    fn test_sort_keys_keeps_blank_lines_in_place() {
+        let content = "c = 1\nb = { z = 1, y = { x = 1, w = 2 } }\n\na = 3\n";
+
+        assert_eq!(sort_str(content, "", &KeyOrder::default()), "a = 3\nb = { z = 1, y = { x = 1, w = 2 } }\n\nc = 1\n");
+        assert_eq!(sort_str(content, "b", &KeyOrder { recursive: true, ..Default::default() }), "c = 1\nb = { y = { w = 2, x = 1 }, z = 1 }\n\na = 3\n");
+    }
+
+    #[test]
+    fn test_sort_keys_errors() {
+        let mut document: DocumentMut = "a = 1\n".parse().unwrap();
+        assert!(sort_keys(&mut document, "a", &KeyOrder::default()).is_err());
+        assert!(sort_keys(&mut document, "b", &KeyOrder::default()).is_err());
+    }
+
+    fn restyle_str(content: &str, key: &str, style: TableStyle) -> String {
+        let mut document: DocumentMut = content.parse().unwrap();
+        restyle(&mut document, key, style).unwrap();
+        let restyled = document.to_string();
+        assert_eq!(toml::from_str::<toml::Value>(&restyled).unwrap(), toml::from_str::<toml::Value>(content).unwrap());
+        restyled
+    }
+
+    #[test]
+    fn test_restyle() {
+        let content = "[server]\n# Public port\nport = 0x50\n\n[server.tls]\ncert = 'a.pem'\n";
+
+        assert_eq!(restyle_str(content, "server", TableStyle::Dotted), "# Public port\nserver.port = 0x50\nserver.tls.cert = 'a.pem'\n");
+        assert_eq!(restyle_str(content, "server.tls", TableStyle::Inline), "[server]\n# Public port\nport = 0x50\ntls = { cert = 'a.pem' }\n");
+        assert_eq!(restyle_str(&content.replace("# Public port\n", ""), "server", TableStyle::Inline), "server = { port = 0x50, tls = { cert = 'a.pem' } }\n");
+
+        // An inline table cannot hold the comment.
+        let mut document: DocumentMut = content.parse().unwrap();
+        assert!(restyle(&mut document, "server", TableStyle::Inline).is_err());
+        assert!(restyle(&mut document, "", TableStyle::Inline).is_err());
+
+        let content = "server = { port = 80, tls = { cert = 'a.pem' } }\n";
+        assert_eq!(restyle_str(content, "", TableStyle::Header), "[server]\nport = 80\n\n[server.tls]\ncert = 'a.pem'\n");
+        assert_eq!(restyle_str(content, "server", TableStyle::Dotted), "server.port = 80\nserver.tls.cert = 'a.pem'\n");
+
+        let content = "a = { b = { c = 1 } }\n";
+        assert_eq!(restyle_str(content, "a", TableStyle::Header), "[a.b]\nc = 1\n");
+    }
+
+    #[test]
+    fn test_restyle_arrays_of_tables() {
+        let content = "products = [{ name = 'Hammer' }, { name = 'Nail' }]\n";
+
+        let restyled = restyle_str(content, "products", TableStyle::Header);
+        assert_eq!(restyled, "[[products]]\nname = 'Hammer'\n\n[[products]]\nname = 'Nail'\n");
+        assert_eq!(restyle_str(&restyled, "products", TableStyle::Inline), content);
+    }
+
+    #[test]
+    fn test_restyle_errors() {
+        let mut document: DocumentMut = "a = 1\nt = { u = { v = 1 } }\n[[p]]\nn = 1\n".parse().unwrap();
+        assert!(restyle(&mut document, "a", TableStyle::Header).is_err());
+        assert!(restyle(&mut document, "b", TableStyle::Header).is_err());
+        assert!(restyle(&mut document, "t.u", TableStyle::Header).is_err());
+        assert!(restyle(&mut document, "t.u", TableStyle::Inline).is_ok());
+        assert!(restyle(&mut document, "p", TableStyle::Dotted).is_err());
+        assert!(restyle(&mut document, "p[0]", TableStyle::Inline).is_err());
+    }
+
+    fn rearrange_str(content: &str, key: &str, order: &[usize]) -> String {
+        let mut document: DocumentMut = content.parse().unwrap();
+        rearrange(&mut document, key, order).unwrap();
+        document.to_string()
+    }
+
+    #[test]
+    fn test_rearrange_array() {
+        let content = "ports = [80, # http\n 443, # https\n 22]\n";
+        assert_eq!(rearrange_str(content, "ports", &[2, 1, 0]), "ports = [22,\n 443, # https\n 80 # http\n]\n");
+
+        let content = "hosts = [\n    # primary\n    \"a\",\n    \"b\", # backup\n\n    \"c\",\n]\nflat = [1, 2, 3]\n";
+        assert_eq!(
+            rearrange_str(content, "hosts", &[2, 1, 0]),
+            "hosts = [\n    \"c\",\n    \"b\", # backup\n\n    # primary\n    \"a\",\n]\nflat = [1, 2, 3]\n",
+        );
+        assert_eq!(rearrange_str(content, "flat", &[2, 0]), "hosts = [\n    # primary\n    \"a\",\n    \"b\", # backup\n\n    \"c\",\n]\nflat = [3, 1]\n");
+    }
+
+    #[test]
+    fn test_rearrange_array_of_tables() {
+        let content = "title = \"shop\"\n\n# Hammer\n[[products]]\nname = \"Hammer\"\n\n[[products]] # nail\nname = \"Nail\"\n\n[[products]]\nname = \"Screw\"\n[products.details]\nsize = 3\n\n[other]\nx = 1\n";
+        assert_eq!(rearrange_str(content, "products", &[2, 1, 0]), [
+            "title = \"shop\"",
+            "",
+            "[[products]]",
+            "name = \"Screw\"",
+            "[products.details]",
+            "size = 3",
+            "",
+            "[[products]] # nail",
+            "name = \"Nail\"",
+            "",
+            "# Hammer",
+            "[[products]]",
+            "name = \"Hammer\"",
+            "",
+            "[other]",
+            "x = 1",
+            "",
+        ].join("\n"));
+
+        let mut document: DocumentMut = content.parse().unwrap();
+        assert!(rearrange(&mut document, "title", &[0]).is_err());
+        assert!(rearrange(&mut document, "missing", &[0]).is_err());
+    }
+
+    #[test]
+    fn test_rearrange_sorted_array() {
+        let content = "hosts = [\n  \"web\", # front\n  \"db\", # primary\n  \"api\",\n  \"db\", # replica\n]\n";
+        let mut value: toml::Value = toml::from_str(content).unwrap();
+        let unique = crate::operations::SortOptions { unique: true, ..Default::default() };
+        let order = crate::operations::sort_array(&mut value, "hosts", &unique).unwrap();
+
+        assert_eq!(
+            rearrange_str(content, "hosts", &order),
+            "hosts = [\n  \"api\",\n  \"db\", # primary\n  \"web\", # front\n]\n",
+        );
+    }
+}
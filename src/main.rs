@@ -1,73 +1,857 @@
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::io::{self, Read};
 use toml::Value;
 
+mod document;
 mod operations;
+mod format;
+mod tree;
 
 fn print_usage() {
     eprintln!("Usage: ctoml [OPTION] PATH KEY [VALUE]");
+    eprintln!("       ctoml COMMAND PATH KEY [VALUE]");
     eprintln!("Manipulate TOML files from the command line.");
     eprintln!();
+    eprintln!("Commands:");
+    eprintln!("  insert          Insert VALUE at KEY[INDEX], shifting later elements");
+    eprintln!("  prepend         Insert VALUE at the start of the array KEY");
+    eprintln!("  reorder         Move the element of array KEY at index FROM to index TO");
+    eprintln!("  swap            Swap the elements of array KEY at indices I and J");
+    eprintln!("  reverse         Reverse the order of array KEY");
+    eprintln!("  sort            Sort array KEY; see the sort options below");
+    eprintln!("  upsert          Merge the table VALUE into the entry of array KEY whose");
+    eprintln!("                  --key fields match, or append it when none does");
+    eprintln!("  add-unique      Append VALUE to array KEY unless it is already there");
+    eprintln!("  remove-value    Remove every element equal to VALUE from array KEY");
+    eprintln!("  contains        Exit with status 0 if array KEY contains VALUE, 1 otherwise");
+    eprintln!("  index-of        Print the index of VALUE in array KEY; exit with 1 if missing");
+    eprintln!("  exists          Exit with status 0 if KEY exists, 1 otherwise");
+    eprintln!("  type            Print the TOML type of KEY");
+    eprintln!("  len             Print the length of the array, table or string KEY");
+    eprintln!("  keys            Print the keys of table KEY, one per line");
+    eprintln!("  get             Print KEY [KEY...] as one object; name=KEY renames an entry");
+    eprintln!("  diff            Compare the values of PATH and NEW_PATH; exit with status 1 if");
+    eprintln!("                  they differ");
+    eprintln!("  merge3          Merge the changes from BASE to THEIRS into OURS, key by key;");
+    eprintln!("                  exit with status 1 if some keys conflict");
+    eprintln!("  eq              Exit with status 0 if PATH and OTHER_PATH (or their KEY) hold");
+    eprintln!("                  the same values, ignoring formatting, comments and key order");
+    eprintln!("  hash            Print a SHA-256 digest of the values of PATH (or its KEY)");
+    eprintln!("  tree            Print the structure below KEY (default: all) as a tree");
+    eprintln!("  fmt             Lay out the whole file again; see the layout options below");
+    eprintln!("  restyle         Rewrite table KEY (default: all) and the tables below it as");
+    eprintln!("                  --inline, --header or --dotted tables, keeping the data");
+    eprintln!("  sort-keys       Sort the keys of table KEY (default: all) alphabetically;");
+    eprintln!("                  [[array of tables]] entries keep their place");
+    eprintln!("  grep            Print the KEY = VALUE lines of values matching --key-pattern,");
+    eprintln!("                  --value and --kind; exit with status 1 if nothing matches");
+    eprintln!("  flatten         Print every value below KEY (default: all) as a KEY = VALUE line");
+    eprintln!("  unflatten       Rebuild TOML from KEY = VALUE lines in PATH (default: stdin)");
+    eprintln!("  mv              Move or rename KEY to DEST (DEST[] appends to an array)");
+    eprintln!("  cp              Copy KEY to DEST (DEST[] appends to an array); copy between");
+    eprintln!("                  files with: ctoml cp SOURCE.toml:KEY PATH:DEST");
+    eprintln!();
     eprintln!("Options:");
     eprintln!("  -r, --remove    Remove the specified key");
+    eprintln!("  -t, --type TYPE Write VALUE as TYPE: string, integer, float, boolean,");
+    eprintln!("                  datetime, datetime-local, date or time");
+    eprintln!("  --value-file PATH  Read VALUE from a file, stored as a string");
+    eprintln!("  --value-stdin      Read VALUE from standard input, stored as a string");
+    eprintln!("  --parse            Parse VALUE, the file or stdin content as a TOML or JSON value");
+    eprintln!("  --header        Write new tables as [KEY] and append tables to arrays as");
+    eprintln!("                  [[KEY]] entries, even if the array is written inline");
+    eprintln!("  --inline        Write new tables as KEY = {{ ... }} and new arrays of tables inline");
+    eprintln!("  --dotted        Write new tables as dotted KEY.SUBKEY = VALUE lines");
+    eprintln!("                  (for restyle: the style to convert to)");
+    eprintln!("  --first, --last Put KEY first or last among the keys of its table");
+    eprintln!("  --before SIBLING, --after SIBLING");
+    eprintln!("                  Put KEY just before or after SIBLING, a key of the same table");
+    eprintln!("  --key FIELD[,FIELD] Fields identifying the entry to upsert (repeatable)");
+    eprintln!("  -f, --force     Let mv and cp overwrite an existing DEST");
+    eprintln!("  --format FORMAT Output of get: json (default) or toml; of diff: human");
+    eprintln!("                  (default), json or patch");
+    eprintln!("  --depth N       Limit tree to N levels below KEY");
+    eprintln!("  --key-pattern PATTERN  For grep: match the full key against a regular expression");
+    eprintln!("  --value PATTERN For grep: match the value against a regular expression");
+    eprintln!("  --glob          For grep: use * and ? wildcards matching the whole text instead");
+    eprintln!("  --kind TYPE[,TYPE]  For grep: only match values of these types (as for --type),");
+    eprintln!("                  or empty-array and empty-table (repeatable)");
+    eprintln!();
+    eprintln!("Sort options:");
+    eprintln!("  --unique        Drop duplicate elements");
+    eprintln!("  --reverse       Sort in descending order");
+    eprintln!("  --natural       Compare numbers inside strings by value (web2 before web10)");
+    eprintln!("  --numeric       Compare strings holding numbers by their value");
+    eprintln!("  --by FIELD      Sort an array of tables by FIELD");
+    eprintln!("  --check         Exit with status 1 if the array is not sorted, without writing");
+    eprintln!();
+    eprintln!("Key sorting options (sort-keys):");
+    eprintln!("  --recursive     Sort the tables below KEY as well");
+    eprintln!("  --priority KEY[,KEY]  Put these keys first, in this order (repeatable)");
+    eprintln!();
+    eprintln!("Layout options (fmt):");
+    eprintln!("  --sort-keys     Sort the keys of every table, and the tables themselves");
+    eprintln!("  --indent N      Indent tables and their keys by N spaces per level of nesting");
+    eprintln!("  --width N       Write arrays one element per line when their line would be");
+    eprintln!("                  longer than N");
+    eprintln!("  --trailing-comma  End arrays written one element per line with a comma");
+    eprintln!("  --inline-max N  Write tables of up to N values inline, larger ones as [headers]");
+    eprintln!("  --blank-lines N Put N blank lines before each table (default: 1)");
+    eprintln!("  --check         Exit with status 1 if the file is not formatted, without writing");
     eprintln!();
     eprintln!("Arguments:");
     eprintln!("  PATH            Path to the TOML file");
     eprintln!("  KEY             Key to read, write, or remove (use dot notation for nested keys)");
-    eprintln!("  VALUE           Value to write (required for write operations)");
+    eprintln!("  VALUE           Value to write (required for write operations);");
+    eprintln!("                  @FILE.toml:KEY takes the value at KEY in another file;");
+    eprintln!("                  start VALUE with @@ to write a text starting with @");
     eprintln!();
     eprintln!("Examples:");
     eprintln!("  ctoml config.toml app.name");
     eprintln!("  ctoml config.toml app.version 1.0.0");
     eprintln!("  ctoml config.toml database.ports[] 5432");
+    eprintln!("  ctoml config.toml release.date 2024-05-01T10:00:00Z");
+    eprintln!("  ctoml -t date config.toml release.day now");
+    eprintln!("  ctoml config.toml tls.certificate --value-file cert.pem");
+    eprintln!("  ctoml config.toml server.limits.connections 100 --dotted");
+    eprintln!("  ctoml Cargo.toml package.description 'A tool' --after version");
     eprintln!("  ctoml -r config.toml app.deprecated_field");
+    eprintln!("  ctoml insert config.toml database.ports[1] 8080");
+    eprintln!("  ctoml prepend config.toml database.ports ...[80, 443]");
+    eprintln!("  ctoml reorder config.toml products 2 0");
+    eprintln!("  ctoml sort config.toml allowed_hosts --unique --natural");
+    eprintln!("  ctoml sort config.toml products --by name --check");
+    eprintln!("  ctoml exists config.toml database.ports || echo missing");
+    eprintln!("  ctoml get config.toml package.name v=package.version --format toml");
+    eprintln!("  ctoml diff config.toml config.new.toml --format patch");
+    eprintln!("  ctoml merge3 base.toml ours.toml theirs.toml");
+    eprintln!("  ctoml hash Cargo.toml dependencies");
+    eprintln!("  ctoml tree config.toml database --depth 1");
+    eprintln!("  ctoml fmt config.toml --width 80 --trailing-comma --check");
+    eprintln!("  ctoml restyle config.toml database --dotted");
+    eprintln!("  ctoml sort-keys Cargo.toml dependencies");
+    eprintln!("  ctoml sort-keys Cargo.toml --recursive --priority name,version");
+    eprintln!("  ctoml grep config.toml --key-pattern 'port$' --value 8080");
+    eprintln!("  ctoml mv config.toml database services.db");
+    eprintln!("  ctoml cp base.toml:logging service.toml:logging");
+    eprintln!("  ctoml add-unique config.toml features ...[tls, http2]");
+    eprintln!("  ctoml contains config.toml features tls");
+    eprintln!("  ctoml upsert config.toml products '{{name: \"Nail\", sku: 1}}' --key name");
     eprintln!();
     eprintln!("For more information, visit: https://github.com/sociation/ctoml");
 }
 
+#[derive(Default)]
+struct Options {
+    remove: bool,
+    value_type: Option<operations::ValueType>,
+    value_file: Option<String>,
+    value_stdin: bool,
+    parse: bool,
+    sort: operations::SortOptions,
+    check: bool,
+    keys: Vec<String>,
+    force: bool,
+    depth: Option<usize>,
+    format: Option<String>,
+    key_pattern: Option<String>,
+    value_pattern: Option<String>,
+    glob: bool,
+    kinds: Vec<&'static str>,
+    layout: format::FormatOptions,
+    key_order: format::KeyOrder,
+    table_style: Option<document::TableStyle>,
+    placement: Option<document::Placement>,
+    /// Every option given, as typed.
+    flags: Vec<String>,
+}
+
+/// Options only some commands use, and those commands. Given to any other
+/// command they are an error rather than silently ignored.
+/// `""` stands for the default `ctoml PATH KEY [VALUE]` form.
+const COMMAND_FLAGS: &[(&[&str], &[&str])] = &[
+    (&["-r", "--remove"], &[""]),
+    (&["-t", "--type", "--value-file", "--value-stdin", "--parse"],
+     &["", "insert", "prepend", "upsert", "add-unique", "remove-value", "contains", "index-of"]),
+    (&["--header", "--inline", "--dotted"], &["", "restyle"]),
+    (&["--first", "--last", "--before", "--after"], &[""]),
+    (&["--unique"], &["sort"]),
+    (&["--reverse"], &["sort"]),
+    (&["--natural"], &["sort"]),
+    (&["--numeric"], &["sort"]),
+    (&["--by"], &["sort"]),
+    (&["--check"], &["sort", "fmt"]),
+    (&["--key"], &["upsert"]),
+    (&["--key-pattern"], &["grep"]),
+    (&["--value"], &["grep"]),
+    (&["--glob"], &["grep"]),
+    (&["--kind"], &["grep"]),
+    (&["-f", "--force"], &["mv", "cp"]),
+    (&["--depth"], &["tree"]),
+    (&["--format"], &["get", "diff"]),
+    (&["--sort-keys", "--indent", "--width", "--trailing-comma", "--inline-max", "--blank-lines"], &["fmt"]),
+    (&["--recursive", "--priority"], &["sort-keys"]),
+];
+
+/// Commands that answer with exit status 1; their errors exit with status 2.
+const STATUS_COMMANDS: &[&str] = &["exists", "contains", "index-of", "eq", "diff", "grep", "merge3"];
+
+/// Every COMMAND; anything else in that place is the PATH of the default form.
+const COMMANDS: &[&str] = &[
+    "insert", "prepend", "reorder", "swap", "reverse", "sort", "upsert", "exists", "type", "len", "keys",
+    "get", "tree", "fmt", "sort-keys", "restyle", "diff", "merge3", "eq", "hash", "grep", "flatten",
+    "unflatten", "mv", "cp", "add-unique", "remove-value", "contains", "index-of",
+];
+
+/// The value to write: the VALUE argument as typed, or a value that has
+/// already been parsed because of `--type`, `--value-file` or `--value-stdin`.
+enum NewValue<'a> {
+    Raw(&'a str),
+    Parsed(Value),
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = env::args().collect();
-    
+    let mut options = Options::default();
+    let mut args: Vec<String> = Vec::new();
+
+    let mut raw_args = env::args().skip(1);
+    while let Some(arg) = raw_args.next() {
+        match arg.as_str() {
+            "-r" | "--remove" => options.remove = true,
+            "-t" | "--type" => {
+                let name = raw_args.next().ok_or("--type requires a TYPE argument")?;
+                options.value_type = Some(name.parse()?);
+            },
+            "--value-file" => options.value_file = Some(raw_args.next().ok_or("--value-file requires a PATH argument")?),
+            "--value-stdin" => options.value_stdin = true,
+            "--parse" => options.parse = true,
+            "--unique" => options.sort.unique = true,
+            "--reverse" => options.sort.reverse = true,
+            "--natural" => options.sort.order = operations::SortOrder::Natural,
+            "--numeric" => options.sort.order = operations::SortOrder::Numeric,
+            "--by" => options.sort.by = Some(raw_args.next().ok_or("--by requires a FIELD argument")?),
+            "--check" => options.check = true,
+            "-f" | "--force" => options.force = true,
+            "--depth" => {
+                let depth = raw_args.next().ok_or("--depth requires a number")?;
+                options.depth = Some(depth.parse().map_err(|_| format!("Invalid depth '{}'", depth))?);
+            },
+            "--key" => {
+                let fields = raw_args.next().ok_or("--key requires a FIELD argument")?;
+                options.keys.extend(fields.split(',').map(|field| field.trim().to_string()));
+            },
+            "--key-pattern" => options.key_pattern = Some(raw_args.next().ok_or("--key-pattern requires a PATTERN argument")?),
+            "--kind" => {
+                let kinds = raw_args.next().ok_or("--kind requires a TYPE argument")?;
+                for kind in kinds.split(',') {
+                    options.kinds.push(operations::kind_filter(kind.trim())?);
+                }
+            },
+            "--value" => options.value_pattern = Some(raw_args.next().ok_or("--value requires a PATTERN argument")?),
+            "--glob" => options.glob = true,
+            "--format" => options.format = Some(raw_args.next().ok_or("--format requires a FORMAT argument")?),
+            "--sort-keys" => options.layout.sort_keys = true,
+            "--indent" => options.layout.indent = number_argument("--indent", raw_args.next())?,
+            "--width" => options.layout.width = Some(number_argument("--width", raw_args.next())?),
+            "--trailing-comma" => options.layout.trailing_comma = true,
+            "--inline-max" => options.layout.inline_max = Some(number_argument("--inline-max", raw_args.next())?),
+            "--header" => options.table_style = Some(document::TableStyle::Header),
+            "--inline" => options.table_style = Some(document::TableStyle::Inline),
+            "--dotted" => options.table_style = Some(document::TableStyle::Dotted),
+            "--first" => options.placement = Some(document::Placement::First),
+            "--last" => options.placement = Some(document::Placement::Last),
+            "--before" => {
+                let sibling = raw_args.next().ok_or("--before requires a KEY argument")?;
+                options.placement = Some(document::Placement::Before(sibling));
+            },
+            "--after" => {
+                let sibling = raw_args.next().ok_or("--after requires a KEY argument")?;
+                options.placement = Some(document::Placement::After(sibling));
+            },
+            "--recursive" => options.key_order.recursive = true,
+            "--priority" => {
+                let keys = raw_args.next().ok_or("--priority requires a KEY[,KEY] argument")?;
+                options.key_order.priority.extend(keys.split(',').map(|key| key.trim().to_string()));
+            },
+            "--blank-lines" => options.layout.blank_lines = number_argument("--blank-lines", raw_args.next())?,
+            _ => {
+                args.push(arg);
+                continue;
+            },
+        }
+        options.flags.push(arg);
+    }
+
+    let command = args.first().map(String::as_str).filter(|command| COMMANDS.contains(command)).unwrap_or("");
+    let result = run(&args, &options, command);
+    if let Err(error) = &result {
+        if options.check || STATUS_COMMANDS.contains(&command) {
+            eprintln!("Error: {:?}", error);
+            std::process::exit(2);
+        }
+    }
+    result
+}
+
+fn run(args: &[String], options: &Options, command: &str) -> Result<(), Box<dyn std::error::Error>> {
+    for (flags, commands) in COMMAND_FLAGS {
+        if let Some(flag) = options.flags.iter().find(|flag| flags.contains(&flag.as_str())) {
+            if !commands.contains(&command) {
+                let names: Vec<&str> = commands.iter()
+                    .map(|name| if name.is_empty() { "ctoml PATH KEY [VALUE]" } else { name })
+                    .collect();
+                let names = match names.split_last() {
+                    Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+                    _ => names.concat(),
+                };
+                return Err(format!("{} only applies to {}", flag, names).into());
+            }
+        }
+    }
+
+    match args.first().map(String::as_str) {
+        Some("insert") | Some("prepend") => run_insert(args, options),
+        Some("reorder") | Some("swap") | Some("reverse") => run_reorder(args),
+        Some("sort") => run_sort(args, options),
+        Some("upsert") => run_upsert(args, options),
+        Some("exists") | Some("type") | Some("len") | Some("keys") => run_inspect(args),
+        Some("get") => run_get(args, options),
+        Some("tree") => run_tree(args, options),
+        Some("fmt") => run_fmt(args, options),
+        Some("sort-keys") => run_sort_keys(args, options),
+        Some("restyle") => run_restyle(args, options),
+        Some("diff") => run_diff(args, options),
+        Some("merge3") => run_merge3(args),
+        Some("eq") => run_eq(args),
+        Some("hash") => run_hash(args),
+        Some("grep") => run_grep(args, options),
+        Some("flatten") => run_flatten(args),
+        Some("unflatten") => run_unflatten(args),
+        Some("mv") | Some("cp") => run_copy(args, options),
+        Some("add-unique") | Some("remove-value") | Some("contains") | Some("index-of") => run_members(args, options),
+        _ => run_default(args, options),
+    }
+}
+
+fn usage_error() -> ! {
+    print_usage();
+    std::process::exit(2);
+}
+
+fn number_argument(flag: &str, argument: Option<String>) -> Result<usize, Box<dyn std::error::Error>> {
+    let argument = argument.ok_or_else(|| format!("{} requires a number", flag))?;
+    argument.parse().map_err(|_| format!("Invalid number '{}' for {}", argument, flag).into())
+}
+
+/// `ctoml [OPTION] PATH KEY [VALUE]`: read, write or remove a single key.
+fn run_default(args: &[String], options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 2 {
+        usage_error();
+    }
+
+    let path = &args[0];
+    let key = &args[1];
+    let value = args.get(2);
+
+    // Only writing a value creates a missing file.
+    let writes = !options.remove && (value.is_some() || options.value_file.is_some() || options.value_stdin);
+    let mut document = if writes { document::load_or_new(path)? } else { document::load(path)? };
+    document.style = options.table_style;
+    let toml_value = &mut document.value;
+
+    if options.remove {
+        if value.is_some() || options.value_file.is_some() || options.value_stdin {
+            return Err("--remove does not take a VALUE".into());
+        }
+        operations::remove_value(toml_value, key)?;
+    } else if let Some(new) = new_value(options, value)? {
+        match new {
+            NewValue::Raw(val) => {
+                operations::set_value(toml_value, key, val)?;
+                document.literals.push((key.clone(), val.to_string()));
+            },
+            NewValue::Parsed(parsed) => operations::set_parsed_value(toml_value, key, parsed, false)?,
+        }
+        document.placement = options.placement.clone().map(|placement| (key.clone(), placement));
+    } else {
+        let result = operations::get_value(toml_value, key);
+        println!("{}", result);
+        return Ok(());
+    }
+
+    document::save(path, &mut document)
+}
+
+/// `ctoml insert PATH KEY[INDEX] VALUE` and `ctoml prepend PATH KEY VALUE`.
+fn run_insert(args: &[String], options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 3 {
+        usage_error();
+    }
+
+    let path = &args[1];
+    let key = if args[0] == "prepend" { format!("{}[0]", args[2]) } else { args[2].clone() };
+    let new = new_value(options, args.get(3))?.ok_or("VALUE is required")?;
+
+    let mut document = document::load_or_new(path)?;
+    let inserted = match new {
+        NewValue::Raw(val) => {
+            let inserted = operations::insert_value(&mut document.value, &key, val)?;
+            document.literals.push((key.clone(), val.to_string()));
+            inserted
+        },
+        NewValue::Parsed(parsed) => operations::insert_parsed_value(&mut document.value, &key, parsed, false)?,
+    };
+    let (array_key, _) = operations::split_last_index(&key)?;
+    document::insert_elements(&mut document, array_key, inserted)?;
+    document::save(path, &mut document)
+}
+
+/// `ctoml reorder PATH KEY FROM TO`, `ctoml swap PATH KEY I J` and
+/// `ctoml reverse PATH KEY`.
+fn run_reorder(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let expected = if args[0] == "reverse" { 3 } else { 5 };
+    if args.len() != expected {
+        usage_error();
+    }
+
+    let path = &args[1];
+    let key = &args[2];
+    let index = |i: usize| args[i].parse::<usize>().map_err(|_| format!("Invalid array index '{}'", args[i]));
+
+    let mut document = document::load(path)?;
+    let order = match args[0].as_str() {
+        "reorder" => operations::move_element(&mut document.value, key, index(3)?, index(4)?)?,
+        "swap" => operations::swap_elements(&mut document.value, key, index(3)?, index(4)?)?,
+        _ => operations::reverse_array(&mut document.value, key)?,
+    };
+    format::rearrange(&mut document.edit, key, &order)?;
+    document::save(path, &mut document)
+}
+
+/// `ctoml sort PATH KEY [--unique] [--reverse] [--natural|--numeric] [--by FIELD] [--check]`.
+fn run_sort(args: &[String], options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() != 3 {
+        usage_error();
+    }
+
+    let path = &args[1];
+    let key = &args[2];
+
+    let mut document = document::load(path)?;
+    if options.check {
+        if !operations::is_sorted(&document.value, key, &options.sort)? {
+            eprintln!("{} is not sorted", key);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let order = operations::sort_array(&mut document.value, key, &options.sort)?;
+    format::rearrange(&mut document.edit, key, &order)?;
+    document::save(path, &mut document)
+}
+
+/// `ctoml upsert PATH KEY VALUE --key FIELD[,FIELD]`.
+fn run_upsert(args: &[String], options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 3 {
+        usage_error();
+    }
+
+    let path = &args[1];
+    let key = &args[2];
+    let new = new_value(options, args.get(3))?.ok_or("VALUE is required")?;
+
+    let mut document = document::load_or_new(path)?;
+    match new {
+        NewValue::Raw(val) => {
+            let index = operations::upsert_value(&mut document.value, key, val, &options.keys)?;
+            document.literals.push((format!("{}[{}]", key, index), val.to_string()));
+        },
+        NewValue::Parsed(parsed) => {
+            operations::upsert_parsed_value(&mut document.value, key, parsed, &options.keys)?;
+        },
+    }
+    document::save(path, &mut document)
+}
+
+/// `ctoml add-unique PATH KEY VALUE`, `ctoml remove-value PATH KEY VALUE`,
+/// `ctoml contains PATH KEY VALUE` and `ctoml index-of PATH KEY VALUE`.
+fn run_members(args: &[String], options: &Options) -> Result<(), Box<dyn std::error::Error>> {
     if args.len() < 3 {
-        print_usage();
+        usage_error();
+    }
+
+    let path = &args[1];
+    let key = &args[2];
+
+    let new = new_value(options, args.get(3))?.ok_or("VALUE is required")?;
+
+    let mut document = if args[0] == "add-unique" { document::load_or_new(path)? } else { document::load(path)? };
+    match (args[0].as_str(), new) {
+        ("add-unique", NewValue::Raw(val)) => {
+            if operations::add_unique_value(&mut document.value, key, val)? > 0 {
+                document.literals.push((format!("{}[]", key), val.to_string()));
+            }
+        },
+        ("add-unique", NewValue::Parsed(parsed)) => {
+            operations::add_unique_parsed_value(&mut document.value, key, parsed, false)?;
+        },
+        ("remove-value", new) => {
+            match new {
+                NewValue::Raw(val) => operations::remove_array_value(&mut document.value, key, val)?,
+                NewValue::Parsed(parsed) => operations::remove_array_parsed_value(&mut document.value, key, parsed, false)?,
+            };
+        },
+        ("contains", new) => {
+            let found = match new {
+                NewValue::Raw(val) => operations::contains_value(&document.value, key, val)?,
+                NewValue::Parsed(parsed) => operations::contains_parsed_value(&document.value, key, &parsed)?,
+            };
+            std::process::exit(if found { 0 } else { 1 });
+        },
+        (_, new) => {
+            let index = match new {
+                NewValue::Raw(val) => operations::index_of(&document.value, key, val)?,
+                NewValue::Parsed(parsed) => operations::index_of_parsed_value(&document.value, key, &parsed)?,
+            };
+            match index {
+                Some(index) => {
+                    println!("{}", index);
+                    return Ok(());
+                },
+                None => std::process::exit(1),
+            }
+        },
+    }
+    document::save(path, &mut document)
+}
+
+/// `ctoml exists PATH KEY`, `ctoml type PATH KEY`, `ctoml len PATH KEY` and
+/// `ctoml keys PATH [KEY]`; without KEY they look at the whole document.
+fn run_inspect(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() != 2 && args.len() != 3 {
+        usage_error();
+    }
+
+    let path = &args[1];
+    let key = args.get(2).map(String::as_str).unwrap_or("");
+
+    let document = document::load(path)?;
+    let toml_value = &document.value;
+    match args[0].as_str() {
+        "exists" => std::process::exit(if operations::exists(toml_value, key) { 0 } else { 1 }),
+        "type" => println!("{}", operations::type_of(toml_value, key)?),
+        "len" => println!("{}", operations::length(toml_value, key)?),
+        _ => {
+            for name in operations::keys(toml_value, key)? {
+                println!("{}", name);
+            }
+        },
+    }
+    Ok(())
+}
+
+/// `ctoml get PATH KEY [KEY...] [--format json|toml]`: the keys as one object.
+fn run_get(args: &[String], options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 3 {
+        usage_error();
+    }
+
+    let document = document::load(&args[1])?;
+    let projection = Value::Table(operations::project(&document.value, &args[2..])?);
+    match options.format.as_deref().unwrap_or("json") {
+        "json" => println!("{}", operations::to_json(&projection)),
+        "toml" => print!("{}", document::render(&projection)),
+        format => return Err(format!("Unknown format '{}' (expected json or toml)", format).into()),
+    }
+    Ok(())
+}
+
+/// `ctoml diff OLD NEW [--format human|json|patch]`: exits with status 1 when
+/// the files differ.
+fn run_diff(args: &[String], options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() != 3 {
+        usage_error();
+    }
+
+    let (old_path, new_path) = (&args[1], &args[2]);
+    let (old, new) = (document::load(old_path)?, document::load(new_path)?);
+    let changes = operations::diff(&old.value, &new.value)?;
+    match options.format.as_deref().unwrap_or("human") {
+        "human" => operations::format_human(&changes).iter().for_each(|line| println!("{}", line)),
+        "json" => println!("{}", operations::format_json(&changes)),
+        "patch" => print!("{}", operations::unified_patch(&old.value, &new.value, old_path, new_path)?),
+        format => return Err(format!("Unknown format '{}' (expected human, json or patch)", format).into()),
+    }
+    if !changes.is_empty() {
         std::process::exit(1);
     }
+    Ok(())
+}
 
-    let (remove, arg_index) = if args[1] == "-r" || args[1] == "--remove" {
-        (true, 2)
-    } else {
-        (false, 1)
+/// `ctoml merge3 BASE OURS THEIRS`: writes the merge to OURS, as git expects
+/// from a merge driver, and exits with status 1 if there are conflicts.
+fn run_merge3(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() != 4 {
+        usage_error();
+    }
+
+    let base = document::load(&args[1])?;
+    let mut ours = document::load(&args[2])?;
+    let theirs = document::load(&args[3])?;
+
+    let (merged, conflicts) = operations::merge3(&base.value, &ours.value, &theirs.value);
+    document::sync(&mut ours.edit, &merged);
+    let text = ours.edit.to_string();
+    if conflicts.is_empty() {
+        fs::write(&args[2], text)?;
+        return Ok(());
+    }
+
+    fs::write(&args[2], document::mark_conflicts(&ours.edit, &conflicts))?;
+    for conflict in &conflicts {
+        eprintln!("Conflict in {}", conflict.path);
+    }
+    std::process::exit(1);
+}
+
+/// `ctoml eq PATH OTHER_PATH [KEY]`: exits with status 0 if both hold the same
+/// values, 1 otherwise.
+fn run_eq(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() != 3 && args.len() != 4 {
+        usage_error();
+    }
+
+    let key = args.get(3).map(String::as_str).unwrap_or("");
+    let (a, b) = (document::load(&args[1])?, document::load(&args[2])?);
+    let equal = operations::equivalent(subtree(&a.value, key)?, subtree(&b.value, key)?);
+    std::process::exit(if equal { 0 } else { 1 });
+}
+
+/// `ctoml hash PATH [KEY]`.
+fn run_hash(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() != 2 && args.len() != 3 {
+        usage_error();
+    }
+
+    let key = args.get(2).map(String::as_str).unwrap_or("");
+    let document = document::load(&args[1])?;
+    println!("{}", operations::digest(subtree(&document.value, key)?));
+    Ok(())
+}
+
+/// The value at `key`, or the whole document when `key` is empty.
+fn subtree<'a>(toml_value: &'a Value, key: &str) -> Result<&'a Value, Box<dyn std::error::Error>> {
+    if key.is_empty() {
+        return Ok(toml_value);
+    }
+    operations::lookup(toml_value, key).ok_or_else(|| format!("{} does not exist", key).into())
+}
+
+/// `ctoml tree PATH [KEY] [--depth N]`.
+fn run_tree(args: &[String], options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() != 2 && args.len() != 3 {
+        usage_error();
+    }
+
+    let document = document::load(&args[1])?;
+    let key = args.get(2).map(String::as_str).unwrap_or("");
+    println!("{}", tree::render(&document.edit, key, options.depth)?);
+    Ok(())
+}
+
+/// `ctoml fmt PATH [--check] [LAYOUT OPTIONS]`: with `--check`, exits with
+/// status 1 if the file is not formatted, without writing it.
+fn run_fmt(args: &[String], options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() != 2 {
+        usage_error();
+    }
+
+    let path = &args[1];
+    let mut document = document::load(path)?;
+    let original = document.edit.to_string();
+    let formatted = format::format(&mut document.edit, &options.layout);
+    if options.check {
+        if formatted != original {
+            eprintln!("{} is not formatted", path);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if formatted != original {
+        fs::write(path, formatted)?;
+    }
+    Ok(())
+}
+
+/// `ctoml sort-keys PATH [KEY] [--recursive] [--priority KEY[,KEY]]`.
+fn run_sort_keys(args: &[String], options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() != 2 && args.len() != 3 {
+        usage_error();
+    }
+
+    let path = &args[1];
+    let key = args.get(2).map(String::as_str).unwrap_or("");
+    let mut document = document::load(path)?;
+    format::sort_keys(&mut document.edit, key, &options.key_order)?;
+    document::save(path, &mut document)
+}
+
+/// `ctoml restyle PATH [KEY] --inline|--header|--dotted`.
+fn run_restyle(args: &[String], options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() != 2 && args.len() != 3 {
+        usage_error();
+    }
+    let style = options.table_style.ok_or("restyle requires --inline, --header or --dotted")?;
+
+    let path = &args[1];
+    let key = args.get(2).map(String::as_str).unwrap_or("");
+    let mut document = document::load(path)?;
+    format::restyle(&mut document.edit, key, style)?;
+    document::save(path, &mut document)
+}
+
+/// `ctoml grep PATH [--key-pattern PATTERN] [--value PATTERN] [--glob] [--kind TYPE[,TYPE]]`:
+/// exits with status 1 when nothing matches.
+fn run_grep(args: &[String], options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() != 2 {
+        usage_error();
+    }
+
+    let pattern = |pattern: &String| operations::Pattern::new(pattern, options.glob);
+    let grep_options = operations::GrepOptions {
+        key: options.key_pattern.as_ref().map(pattern).transpose()?,
+        value: options.value_pattern.as_ref().map(pattern).transpose()?,
+        kinds: options.kinds.clone(),
     };
 
-    let path = &args[arg_index];
-    let key = &args[arg_index + 1];
-    let value = args.get(arg_index + 2);
+    let document = document::load(&args[1])?;
+    let matches = operations::grep(&document.value, &grep_options)?;
+    if matches.is_empty() {
+        std::process::exit(1);
+    }
+    for line in matches {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// `ctoml flatten PATH [KEY]`.
+fn run_flatten(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() != 2 && args.len() != 3 {
+        usage_error();
+    }
 
-    let mut toml_content = if Path::new(path).exists() {
-        fs::read_to_string(path)?
+    let document = document::load(&args[1])?;
+    let key = args.get(2).map(String::as_str).unwrap_or("");
+    let value = if key.is_empty() {
+        &document.value
     } else {
-        String::new()
+        operations::lookup(&document.value, key).ok_or_else(|| format!("{} does not exist", key))?
     };
+    for line in operations::flatten(value, key)? {
+        println!("{}", line);
+    }
+    Ok(())
+}
 
-    let mut toml_value: Value = if toml_content.is_empty() {
-        Value::Table(toml::map::Map::new())
-    } else {
-        toml::from_str(&toml_content)?
+/// `ctoml unflatten [PATH]`: prints the rebuilt document.
+fn run_unflatten(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let content = match args.get(1).map(String::as_str) {
+        None | Some("-") => {
+            let mut buffer = String::new();
+            io::stdin().read_to_string(&mut buffer)?;
+            buffer
+        },
+        Some(path) if args.len() == 2 => fs::read_to_string(path)?,
+        _ => usage_error(),
     };
 
-    if remove {
-        operations::remove_value(&mut toml_value, key)?;
-    } else if let Some(val) = value {
-        operations::set_value(&mut toml_value, key, val)?;
+    print!("{}", document::render(&operations::unflatten(&content)?));
+    Ok(())
+}
+
+/// `ctoml mv PATH KEY DEST`, `ctoml cp PATH KEY DEST` and
+/// `ctoml cp SOURCE_PATH:KEY PATH:DEST`.
+fn run_copy(args: &[String], options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() == 3 && args[0] == "cp" {
+        let value = read_reference(&args[1])?;
+        let (path, to) = split_reference(&args[2])?;
+        if to.is_empty() {
+            return Err("cp between files requires a destination key, e.g. service.toml:logging".into());
+        }
+        let mut document = document::load_or_new(path)?;
+        operations::copy_into(&mut document.value, to, value, options.force)?;
+        return document::save(path, &mut document);
+    }
+    if args.len() != 4 {
+        usage_error();
+    }
+
+    let path = &args[1];
+    let (from, to) = (&args[2], &args[3]);
+
+    let mut document = document::load(path)?;
+    if args[0] == "mv" {
+        operations::move_value(&mut document.value, from, to, options.force)?;
     } else {
-        let result = operations::get_value(&toml_value, key);
-        println!("{}", result);
-        return Ok(());
+        operations::copy_value(&mut document.value, from, to, options.force)?;
+    }
+    document::copy_item(&mut document, from, to);
+    document::save(path, &mut document)
+}
+
+/// Splits `file.toml:key` at its last colon.
+fn split_reference(reference: &str) -> Result<(&str, &str), Box<dyn std::error::Error>> {
+    reference.rsplit_once(':').ok_or_else(|| format!("Expected FILE:KEY, got {}", reference).into())
+}
+
+/// Reads the value at `key` from `file.toml:key`; an empty key means the whole file.
+fn read_reference(reference: &str) -> Result<Value, Box<dyn std::error::Error>> {
+    let (path, key) = split_reference(reference)?;
+    let document = document::load(path)?;
+    if key.is_empty() {
+        return Ok(document.value);
     }
+    operations::lookup(&document.value, key)
+        .cloned()
+        .ok_or_else(|| format!("{} does not exist in {}", key, path).into())
+}
 
-    toml_content = toml::to_string(&toml_value)?;
-    fs::write(path, toml_content)?;
+fn new_value<'a>(options: &Options, value: Option<&'a String>) -> Result<Option<NewValue<'a>>, Box<dyn std::error::Error>> {
+    let content = if let Some(file) = &options.value_file {
+        fs::read_to_string(file)?
+    } else if options.value_stdin {
+        let mut buffer = String::new();
+        io::stdin().read_to_string(&mut buffer)?;
+        buffer
+    } else {
+        return match (value, options.value_type) {
+            (Some(val), _) if options.parse => Ok(Some(NewValue::Parsed(operations::parse_content(val)?))),
+            (Some(val), Some(value_type)) => Ok(Some(NewValue::Parsed(operations::parse_typed_value(val, value_type)?))),
+            (Some(val), None) => match val.strip_prefix('@') {
+                // `@@text` is the text `@text`.
+                Some(escaped) if escaped.starts_with('@') => Ok(Some(NewValue::Raw(escaped))),
+                // `@other.toml:key` copies a value from another file, which
+                // has to exist; other text starting with `@` is taken as is.
+                Some(reference) if split_reference(reference).is_ok_and(|(path, _)| path.ends_with(".toml")) => {
+                    Ok(Some(NewValue::Parsed(read_reference(reference)?)))
+                },
+                _ => Ok(Some(NewValue::Raw(val))),
+            },
+            (None, _) => Ok(None),
+        };
+    };
 
-    Ok(())
+    if value.is_some() {
+        return Err("--value-file and --value-stdin cannot be combined with VALUE".into());
+    }
+    let parsed = if options.parse {
+        operations::parse_content(&content)?
+    } else if let Some(value_type) = options.value_type {
+        operations::parse_typed_value(&content, value_type)?
+    } else {
+        Value::String(content)
+    };
+    Ok(Some(NewValue::Parsed(parsed)))
 }
@@ -2,7 +2,37 @@ mod get;
 mod set;
 mod remove;
 mod parse;
+mod insert;
+mod reorder;
+mod sort;
+mod upsert;
+mod members;
+mod copy;
+mod inspect;
+mod flatten;
+mod grep;
+mod diff;
+mod merge;
+mod canonical;
+mod path;
 
-pub use get::get_value;
-pub use set::set_value;
-pub use remove::remove_value;
\ No newline at end of file
+pub use get::{get_value, lookup, project, to_json};
+pub use set::{set_value, set_parsed_value};
+pub use remove::remove_value;
+pub use insert::{insert_value, insert_parsed_value};
+pub use reorder::{move_element, reverse_array, swap_elements};
+pub use canonical::{digest, equivalent};
+pub use merge::{conflict_marker, merge3, Conflict};
+pub use diff::{diff, format_human, format_json, unified_patch};
+pub use grep::{grep, kind_filter, GrepOptions, Pattern};
+pub use flatten::{flatten, unflatten};
+pub use inspect::{exists, keys, length, type_name, type_of};
+pub use copy::{copy_into, copy_value, move_value};
+pub use members::{
+    add_unique_value, add_unique_parsed_value, contains_parsed_value, contains_value, index_of, index_of_parsed_value,
+    remove_array_parsed_value, remove_array_value,
+};
+pub use upsert::{upsert_value, upsert_parsed_value};
+pub use sort::{is_sorted, sort_array, SortOptions, SortOrder};
+pub use parse::{integer_literals, parse_content, parse_typed_value, ValueType};
+pub use path::{array_mut, parse_path, split_last_index, Segment};
\ No newline at end of file
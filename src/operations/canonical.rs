@@ -0,0 +1,151 @@
+//! A canonical text form of a `Value`: the same for any two documents holding
+//! the same data, whatever their formatting, comments or key order, and
+//! different as soon as a value or its type differs.
+
+use sha2::{Digest, Sha256};
+use toml::Value;
+use toml::value::Offset;
+
+/// Every value is tagged with its type, so the string `"1"`, the integer `1`
+/// and the float `1.0` never collide; table keys are sorted.
+pub fn canonical(value: &Value) -> String {
+    let mut encoded = String::new();
+    encode(value, &mut encoded);
+    encoded
+}
+
+/// The SHA-256 of the canonical form, in hexadecimal.
+pub fn digest(value: &Value) -> String {
+    Sha256::digest(canonical(value).as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+pub fn equivalent(a: &Value, b: &Value) -> bool {
+    canonical(a) == canonical(b)
+}
+
+/// Equality that also treats two NaNs as the same value, at any depth.
+pub(super) fn same(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Float(a), Value::Float(b)) => a == b || (a.is_nan() && b.is_nan()),
+        (Value::Array(a), Value::Array(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b)),
+        (Value::Table(a), Value::Table(b)) => {
+            a.len() == b.len() && a.iter().all(|(key, a)| b.get(key).is_some_and(|b| same(a, b)))
+        },
+        _ => a == b,
+    }
+}
+
+fn encode(value: &Value, encoded: &mut String) {
+    match value {
+        Value::String(s) => encoded.push_str(&format!("s{}", serde_json::Value::from(s.as_str()))),
+        Value::Integer(i) => encoded.push_str(&format!("i{}", i)),
+        // All NaNs are the same value in TOML; other floats are compared by their bits.
+        Value::Float(f) if f.is_nan() => encoded.push_str("fnan"),
+        Value::Float(f) => encoded.push_str(&format!("f{:016x}", f.to_bits())),
+        Value::Boolean(b) => encoded.push_str(&format!("b{}", b)),
+        Value::Datetime(dt) => {
+            // `+00:00` and `Z` are the same offset.
+            let mut dt = *dt;
+            if dt.offset == Some(Offset::Custom { minutes: 0 }) {
+                dt.offset = Some(Offset::Z);
+            }
+            encoded.push_str(&format!("d{}", dt));
+        },
+        Value::Array(array) => {
+            encoded.push('[');
+            for (i, value) in array.iter().enumerate() {
+                if i > 0 {
+                    encoded.push(',');
+                }
+                encode(value, encoded);
+            }
+            encoded.push(']');
+        },
+        Value::Table(table) => {
+            // Sorted here rather than relying on the map's own order, which
+            // follows the file with toml's `preserve_order` feature.
+            let mut entries: Vec<_> = table.iter().collect();
+            entries.sort_by_key(|(key, _)| key.as_str());
+            encoded.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    encoded.push(',');
+                }
+                encoded.push_str(&serde_json::Value::from(key.as_str()).to_string());
+                encoded.push(':');
+                encode(value, encoded);
+            }
+            encoded.push('}');
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> Value {
+        toml::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn test_canonical() {
+        let value = parse(r#"
+            b = [1, 1.5, "1", true, 2024-05-01]
+            a = { "key with \"quotes\"" = nan }
+        "#);
+
+        assert_eq!(canonical(&value), r#"{"a":{"key with \"quotes\"":fnan},"b":[i1,f3ff8000000000000,s"1",btrue,d2024-05-01]}"#);
+    }
+
+    #[test]
+    fn test_canonical_sorts_keys() {
+        let value = parse(r#"
+            b = 1
+            "é" = 2
+            B = 3
+            a = { z = 4, y = 5 }
+            10 = 6
+            9 = 7
+        "#);
+
+        assert_eq!(canonical(&value), r#"{"10":i6,"9":i7,"B":i3,"a":{"y":i5,"z":i4},"b":i1,"é":i2}"#);
+    }
+
+    #[test]
+    fn test_equivalent_ignores_formatting() {
+        let a = parse(r#"
+            # Comment
+            [package]
+            name = "ctoml"
+            ports = [0x50, 443]
+            limits = { soft = 1_000, hard = 2e3 }
+        "#);
+        let b = parse(r#"
+            package.limits.hard = 2000.0
+            package.limits.soft = 1000
+            package.name = 'ctoml'
+            package.ports = [
+                80,
+                443,
+            ]
+        "#);
+
+        assert!(equivalent(&a, &b));
+        assert_eq!(digest(&a), digest(&b));
+        assert_eq!(digest(&a).len(), 64);
+    }
+
+    #[test]
+    fn test_equivalent_detects_type_changes() {
+        assert!(equivalent(&parse("a = 2024-05-01 10:00:00+00:00"), &parse("a = 2024-05-01T10:00:00Z")));
+        assert!(!equivalent(&parse("a = 1"), &parse("a = 1.0")));
+        assert!(!equivalent(&parse("a = 1"), &parse("a = '1'")));
+        assert!(!equivalent(&parse("a = 2024-05-01"), &parse("a = '2024-05-01'")));
+        assert!(!equivalent(&parse("a = [1, 2]"), &parse("a = [2, 1]")));
+        assert_ne!(digest(&parse("a = 1")), digest(&parse("a = 2")));
+    }
+}
@@ -0,0 +1,145 @@
+use toml::Value;
+use super::get::lookup;
+use super::path::split_last_index;
+use super::remove::remove_value;
+use super::set::set_parsed_value;
+use std::error::Error;
+
+/// Copies the subtree at `from` to `to`. A destination ending in `[]` appends
+/// to that array; any other existing destination is only replaced with `force`.
+/// An index past the end of an existing array is an error.
+pub fn copy_value(toml_value: &mut Value, from: &str, to: &str, force: bool) -> Result<(), Box<dyn Error>> {
+    let value = lookup(toml_value, from).cloned().ok_or_else(|| format!("{} does not exist", from))?;
+    copy_into(toml_value, to, value, force)
+}
+
+/// Writes a value taken from elsewhere (e.g. another file) to `to`, with the
+/// same destination rules as `copy_value`.
+pub fn copy_into(toml_value: &mut Value, to: &str, value: Value, force: bool) -> Result<(), Box<dyn Error>> {
+    check_destination(toml_value, to, force)?;
+    check_index(toml_value, to)?;
+    set_parsed_value(toml_value, to, value, false)
+}
+
+/// Moves the subtree at `from` to `to`, with the same destination rules as
+/// `copy_value`. The source is removed first, so `mv list[0] list[]` moves the
+/// first element to the end.
+pub fn move_value(toml_value: &mut Value, from: &str, to: &str, force: bool) -> Result<(), Box<dyn Error>> {
+    let value = lookup(toml_value, from).cloned().ok_or_else(|| format!("{} does not exist", from))?;
+    if is_within(to, from) {
+        return Err(format!("Cannot move {} into itself", from).into());
+    }
+    check_destination(toml_value, to, force)?;
+    // The index is checked once the source is gone; `toml_value` is only
+    // changed when the move succeeds.
+    let mut moved = toml_value.clone();
+    remove_value(&mut moved, from)?;
+    check_index(&moved, to)?;
+    set_parsed_value(&mut moved, to, value, false)?;
+    *toml_value = moved;
+    Ok(())
+}
+
+fn check_destination(toml_value: &Value, to: &str, force: bool) -> Result<(), Box<dyn Error>> {
+    if !force && lookup(toml_value, to).is_some() {
+        return Err(format!("{} already exists (use --force to overwrite)", to).into());
+    }
+    Ok(())
+}
+
+/// Refuses `array[n]` with `n` past the end of an existing array, which would
+/// fill the gap with empty arrays; `n` equal to the length appends.
+fn check_index(toml_value: &Value, to: &str) -> Result<(), Box<dyn Error>> {
+    let Ok((key, Some(index))) = split_last_index(to) else {
+        return Ok(());
+    };
+    match lookup(toml_value, key) {
+        Some(Value::Array(array)) if index > array.len() => {
+            Err(format!("{} is past the end of {}, which has {} elements", to, key, array.len()).into())
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Whether `key` is `parent` itself or a key below it.
+fn is_within(key: &str, parent: &str) -> bool {
+    match key.strip_prefix(parent) {
+        Some(rest) => rest.is_empty() || rest.starts_with('.') || rest.starts_with('['),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::get::get_value;
+
+    fn create_sample_toml() -> Value {
+        toml::from_str(r#"
+            [app]
+            old_name = "ctoml"
+            released = 2024-05-01
+
+            [database]
+            ports = [5432, 5433]
+            options = { timeout = 1.5 }
+
+            [[products]]
+            name = "Hammer"
+        "#).unwrap()
+    }
+
+    #[test]
+    fn test_move_value() {
+        let mut toml_value = create_sample_toml();
+
+        move_value(&mut toml_value, "app.old_name", "app.new_name", false).unwrap();
+        assert_eq!(get_value(&toml_value, "app.new_name"), "ctoml");
+        assert_eq!(get_value(&toml_value, "app.old_name"), "");
+
+        move_value(&mut toml_value, "database", "services.db", false).unwrap();
+        assert_eq!(get_value(&toml_value, "services.db.ports[1]"), "5433");
+        assert!(lookup(&toml_value, "services.db.options.timeout").unwrap().is_float());
+        assert!(lookup(&toml_value, "database").is_none());
+
+        move_value(&mut toml_value, "services.db.ports[0]", "services.db.ports[]", false).unwrap();
+        assert_eq!(get_value(&toml_value, "services.db.ports"), "[5433,5432]");
+    }
+
+    #[test]
+    fn test_copy_value() {
+        let mut toml_value = create_sample_toml();
+
+        copy_value(&mut toml_value, "app.released", "products[0].released", false).unwrap();
+        assert!(lookup(&toml_value, "products[0].released").unwrap().is_datetime());
+        assert!(lookup(&toml_value, "app.released").unwrap().is_datetime());
+
+        copy_value(&mut toml_value, "products[0]", "products[]", false).unwrap();
+        assert_eq!(get_value(&toml_value, "products[1].name"), "Hammer");
+
+        copy_value(&mut toml_value, "database.ports[0]", "database.ports[2]", false).unwrap();
+        assert_eq!(get_value(&toml_value, "database.ports"), "[5432,5433,5432]");
+    }
+
+    #[test]
+    fn test_refuse_overwrite() {
+        let mut toml_value = create_sample_toml();
+
+        assert!(copy_value(&mut toml_value, "app.old_name", "products[0].name", false).is_err());
+        assert!(move_value(&mut toml_value, "app", "database", false).is_err());
+        assert!(move_value(&mut toml_value, "app", "app.nested", true).is_err());
+        assert!(move_value(&mut toml_value, "missing", "app.missing", false).is_err());
+        assert!(copy_value(&mut toml_value, "database.ports[0]", "database.ports[3]", false).is_err());
+        assert!(move_value(&mut toml_value, "database.ports[0]", "database.ports[2]", false).is_err());
+        assert_eq!(get_value(&toml_value, "database.ports"), "[5432,5433]");
+        assert_eq!(get_value(&toml_value, "app.old_name"), "ctoml");
+
+        copy_value(&mut toml_value, "app.old_name", "products[0].name", true).unwrap();
+        assert_eq!(get_value(&toml_value, "products[0].name"), "ctoml");
+
+        let logging: Value = toml::from_str("level = 'debug'").unwrap();
+        assert!(copy_into(&mut toml_value, "app", logging.clone(), false).is_err());
+        copy_into(&mut toml_value, "logging", logging, false).unwrap();
+        assert_eq!(get_value(&toml_value, "logging.level"), "debug");
+    }
+}
@@ -0,0 +1,256 @@
+//! Comparing two documents value by value. Both are flattened (see `flatten`),
+//! so formatting, comments and key order play no part, and every difference is
+//! reported at the path of a leaf.
+
+use std::collections::HashSet;
+use std::error::Error;
+use toml::Value;
+use super::canonical::same;
+use super::flatten::{encode_leaf, leaves};
+use super::get::to_json;
+
+#[derive(Debug, PartialEq)]
+pub enum Change<'a> {
+    Added(String, &'a Value),
+    Removed(String, &'a Value),
+    Changed(String, &'a Value, &'a Value),
+}
+
+/// A line of the flattened documents: present in both (by index in each), or
+/// only in the old or the new one.
+enum Step {
+    Both(usize, usize),
+    Old(usize),
+    New(usize),
+}
+
+pub fn diff<'a>(old: &'a Value, new: &'a Value) -> Result<Vec<Change<'a>>, Box<dyn Error>> {
+    let (old_leaves, new_leaves) = (leaves(old, ""), leaves(new, ""));
+    let changes = align(&old_leaves, &new_leaves)
+        .into_iter()
+        .filter_map(|step| match step {
+            Step::Both(i, j) if !same(old_leaves[i].1, new_leaves[j].1) => {
+                Some(Change::Changed(old_leaves[i].0.clone(), old_leaves[i].1, new_leaves[j].1))
+            },
+            Step::Both(..) => None,
+            Step::Old(i) => Some(Change::Removed(old_leaves[i].0.clone(), old_leaves[i].1)),
+            Step::New(j) => Some(Change::Added(new_leaves[j].0.clone(), new_leaves[j].1)),
+        })
+        .collect();
+    Ok(changes)
+}
+
+/// One line per change: `+ path = value`, `- path = value` or `~ path = old -> new`.
+pub fn format_human(changes: &[Change]) -> Vec<String> {
+    changes.iter()
+        .map(|change| match change {
+            Change::Added(path, value) => format!("+ {} = {}", path, encode_leaf(value)),
+            Change::Removed(path, value) => format!("- {} = {}", path, encode_leaf(value)),
+            Change::Changed(path, old, new) => format!("~ {} = {} -> {}", path, encode_leaf(old), encode_leaf(new)),
+        })
+        .collect()
+}
+
+pub fn format_json(changes: &[Change]) -> serde_json::Value {
+    changes.iter()
+        .map(|change| match change {
+            Change::Added(path, value) => serde_json::json!({ "op": "add", "path": path, "value": to_json(value) }),
+            Change::Removed(path, value) => serde_json::json!({ "op": "remove", "path": path, "value": to_json(value) }),
+            Change::Changed(path, old, new) => {
+                serde_json::json!({ "op": "change", "path": path, "old": to_json(old), "new": to_json(new) })
+            },
+        })
+        .collect()
+}
+
+/// A unified diff without context lines of the `ctoml flatten` output of both
+/// documents, labelled `old_name` and `new_name`.
+pub fn unified_patch(old: &Value, new: &Value, old_name: &str, new_name: &str) -> Result<String, Box<dyn Error>> {
+    let (old_leaves, new_leaves) = (leaves(old, ""), leaves(new, ""));
+    let line = |(path, value): &(String, &Value)| format!("{} = {}", path, encode_leaf(value));
+
+    // A changed value is a removal followed by an addition; runs of those,
+    // between lines present unchanged in both, form the hunks.
+    let mut patch = Vec::new();
+    let (mut old_line, mut new_line) = (0, 0);
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let mut hunk_start = (0, 0);
+    let mut steps = align(&old_leaves, &new_leaves).into_iter().peekable();
+    while let Some(step) = steps.next() {
+        if removed.is_empty() && added.is_empty() {
+            hunk_start = (old_line, new_line);
+        }
+        match step {
+            Step::Both(i, j) if same(old_leaves[i].1, new_leaves[j].1) => {},
+            Step::Both(i, j) => {
+                removed.push(i);
+                added.push(j);
+            },
+            Step::Old(i) => removed.push(i),
+            Step::New(j) => added.push(j),
+        }
+        match step {
+            Step::Both(..) => { old_line += 1; new_line += 1; },
+            Step::Old(_) => old_line += 1,
+            Step::New(_) => new_line += 1,
+        }
+
+        let hunk_ends = match steps.peek() {
+            Some(Step::Both(i, j)) => same(old_leaves[*i].1, new_leaves[*j].1),
+            Some(_) => false,
+            None => true,
+        };
+        if hunk_ends && !(removed.is_empty() && added.is_empty()) {
+            patch.push(format!("@@ -{} +{} @@", range(hunk_start.0, removed.len()), range(hunk_start.1, added.len())));
+            patch.extend(removed.drain(..).map(|i| format!("-{}", line(&old_leaves[i]))));
+            patch.extend(added.drain(..).map(|j| format!("+{}", line(&new_leaves[j]))));
+        }
+    }
+
+    if patch.is_empty() {
+        return Ok(String::new());
+    }
+    let header = [format!("--- {}", old_name), format!("+++ {}", new_name)];
+    Ok(header.iter().chain(&patch).map(|line| format!("{}\n", line)).collect())
+}
+
+/// A hunk range: 1-based start and count, where an empty range names the line
+/// before it.
+fn range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+/// Pairs up leaves with the same path. Both lists are in `flatten` order, so a
+/// path missing from the other side is simply skipped over.
+fn align(old: &[(String, &Value)], new: &[(String, &Value)]) -> Vec<Step> {
+    let old_paths: HashSet<&str> = old.iter().map(|(path, _)| path.as_str()).collect();
+    let new_paths: HashSet<&str> = new.iter().map(|(path, _)| path.as_str()).collect();
+
+    let mut steps = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i].0 == new[j].0 {
+            steps.push(Step::Both(i, j));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && !new_paths.contains(old[i].0.as_str()) {
+            steps.push(Step::Old(i));
+            i += 1;
+        } else if j < new.len() && !old_paths.contains(new[j].0.as_str()) {
+            steps.push(Step::New(j));
+            j += 1;
+        } else {
+            // Only reachable if both orders disagree; treat it as a replacement.
+            steps.push(Step::Old(i));
+            i += 1;
+        }
+    }
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn old_toml() -> Value {
+        toml::from_str(r#"
+            [package]
+            name = "ctoml"
+            version = "0.1.0"
+            keywords = ["toml", "cli"]
+
+            [dependencies]
+            toml = "0.8"
+            serde_json = "1.0"
+        "#).unwrap()
+    }
+
+    fn new_toml() -> Value {
+        toml::from_str(r#"
+            [dependencies]
+            serde_json = "1.0"
+            regex = "1"
+
+            [package]
+            version = "0.2.0"
+            name = "ctoml"
+            keywords = ["toml"]
+        "#).unwrap()
+    }
+
+    #[test]
+    fn test_diff() {
+        let (old, new) = (old_toml(), new_toml());
+
+        assert_eq!(diff(&old, &new).unwrap(), vec![
+            Change::Added("dependencies.regex".to_string(), &Value::String("1".to_string())),
+            Change::Removed("dependencies.toml".to_string(), &Value::String("0.8".to_string())),
+            Change::Removed("package.keywords[1]".to_string(), &Value::String("cli".to_string())),
+            Change::Changed("package.version".to_string(), &Value::String("0.1.0".to_string()), &Value::String("0.2.0".to_string())),
+        ]);
+        assert!(diff(&old, &old_toml()).unwrap().is_empty());
+
+        let empty = Value::Table(toml::Table::new());
+        let removed = diff(&old, &empty).unwrap();
+        assert_eq!(removed.len(), 6);
+        assert!(removed.iter().all(|change| matches!(change, Change::Removed(path, _) if !path.is_empty())));
+        assert!(diff(&empty, &Value::Table(toml::Table::new())).unwrap().is_empty());
+        assert_eq!(unified_patch(&empty, &empty, "a.toml", "b.toml").unwrap(), "");
+    }
+
+    #[test]
+    fn test_format_human_and_json() {
+        let (old, new) = (old_toml(), new_toml());
+        let changes = diff(&old, &new).unwrap();
+
+        assert_eq!(format_human(&changes), vec![
+            "+ dependencies.regex = \"1\"",
+            "- dependencies.toml = \"0.8\"",
+            "- package.keywords[1] = \"cli\"",
+            "~ package.version = \"0.1.0\" -> \"0.2.0\"",
+        ]);
+        assert_eq!(format_json(&changes)[3].to_string(), r#"{"new":"0.2.0","old":"0.1.0","op":"change","path":"package.version"}"#);
+    }
+
+    #[test]
+    fn test_unified_patch() {
+        let (old, new) = (old_toml(), new_toml());
+
+        assert_eq!(unified_patch(&old, &new, "a.toml", "b.toml").unwrap(), [
+            "--- a.toml",
+            "+++ b.toml",
+            "@@ -0,0 +1 @@",
+            "+dependencies.regex = \"1\"",
+            "@@ -2 +2,0 @@",
+            "-dependencies.toml = \"0.8\"",
+            "@@ -4 +3,0 @@",
+            "-package.keywords[1] = \"cli\"",
+            "@@ -6 +5 @@",
+            "-package.version = \"0.1.0\"",
+            "+package.version = \"0.2.0\"",
+            "",
+        ].join("\n"));
+        assert_eq!(unified_patch(&old, &old_toml(), "a.toml", "b.toml").unwrap(), "");
+    }
+
+    #[test]
+    fn test_diff_quotes_keys_with_dots() {
+        let old: Value = toml::from_str("[hosts]\n\"db.example.com\" = 5432\n").unwrap();
+        let new: Value = toml::from_str("[hosts]\n\"db.example.com\" = 5433\n").unwrap();
+
+        assert!(diff(&old, &old).unwrap().is_empty());
+        assert_eq!(format_human(&diff(&old, &new).unwrap()), vec![r#"~ hosts."db.example.com" = 5432 -> 5433"#]);
+        assert_eq!(unified_patch(&old, &new, "a.toml", "b.toml").unwrap(), [
+            "--- a.toml",
+            "+++ b.toml",
+            "@@ -1 +1 @@",
+            r#"-hosts."db.example.com" = 5432"#,
+            r#"+hosts."db.example.com" = 5433"#,
+            "",
+        ].join("\n"));
+    }
+}
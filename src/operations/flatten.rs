@@ -0,0 +1,219 @@
+//! One `path = value` line per leaf, using the key syntax of `set_value`, so a
+//! document can be grepped and diffed line by line and rebuilt afterwards.
+
+use toml::{Table, Value};
+use super::path::quoted_end;
+use super::set::set_value;
+use std::error::Error;
+
+/// Lists every leaf below `toml_value` as `prefix.path = value`. Empty arrays
+/// and tables are leaves too (`[]`, `{}`), so nothing is lost on the way back.
+pub fn flatten(toml_value: &Value, prefix: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    Ok(leaves(toml_value, prefix)
+        .into_iter()
+        .map(|(path, value)| format!("{} = {}", path, encode_leaf(value)))
+        .collect())
+}
+
+/// Every leaf below `toml_value` with its full path, in the order `flatten`
+/// prints them. Keys the path syntax cannot hold, such as `"example.com"`,
+/// are quoted.
+pub(super) fn leaves<'a>(toml_value: &'a Value, prefix: &str) -> Vec<(String, &'a Value)> {
+    let mut leaves = Vec::new();
+    collect_leaves(toml_value, prefix, &mut leaves);
+    leaves
+}
+
+/// A leaf as it appears on the right of `=` in `flatten` output.
+pub(super) fn encode_leaf(value: &Value) -> String {
+    match value {
+        Value::Table(_) => "{}".to_string(),
+        Value::Array(_) => "[]".to_string(),
+        Value::String(s) => encode_string(s),
+        Value::Float(f) => encode_float(*f),
+        Value::Datetime(dt) => dt.to_string(),
+        _ => value.to_string(),
+    }
+}
+
+/// Rebuilds a document from `path = value` lines, skipping blank lines and
+/// `#` comments.
+pub fn unflatten(content: &str) -> Result<Value, Box<dyn Error>> {
+    let mut toml_value = Value::Table(Table::new());
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = split_assignment(line).ok_or_else(|| format!("Line {}: expected PATH = VALUE, got {}", number + 1, line))?;
+        set_value(&mut toml_value, key.trim(), value.trim()).map_err(|e| format!("Line {}: {}", number + 1, e))?;
+    }
+    Ok(toml_value)
+}
+
+fn collect_leaves<'a>(value: &'a Value, path: &str, leaves: &mut Vec<(String, &'a Value)>) {
+    match value {
+        Value::Table(table) if !table.is_empty() => {
+            for (key, value) in table {
+                let key = if is_bare(key) { key.clone() } else { encode_string(key) };
+                let path = if path.is_empty() { key } else { format!("{}.{}", path, key) };
+                collect_leaves(value, &path, leaves);
+            }
+        },
+        Value::Array(array) if !array.is_empty() => {
+            for (i, value) in array.iter().enumerate() {
+                collect_leaves(value, &format!("{}[{}]", path, i), leaves);
+            }
+        },
+        // An empty document has no path to write, and so no leaves.
+        _ if path.is_empty() => {},
+        _ => leaves.push((path.to_string(), value)),
+    }
+}
+
+/// Splits a `path = value` line at the first `=` that is not part of a quoted key.
+fn split_assignment(line: &str) -> Option<(&str, &str)> {
+    let mut i = 0;
+    while let Some(c) = line[i..].chars().next() {
+        if i == 0 || line[..i].ends_with('.') {
+            if let Some(end) = quoted_end(&line[i..]) {
+                i += end;
+                continue;
+            }
+        }
+        if c == '=' {
+            return Some((&line[..i], &line[i + 1..]));
+        }
+        i += c.len_utf8();
+    }
+    None
+}
+
+/// Whether `key` can be written as it is in a path, rather than quoted.
+fn is_bare(key: &str) -> bool {
+    !(key.is_empty()
+        || key.trim() != key
+        || key.starts_with(['#', '"', '\''])
+        || key.contains(['.', '[', ']', '=', '\n', '\r']))
+}
+
+/// A single-line TOML basic string.
+fn encode_string(s: &str) -> String {
+    let mut encoded = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => encoded.push_str("\\\""),
+            '\\' => encoded.push_str("\\\\"),
+            '\n' => encoded.push_str("\\n"),
+            '\r' => encoded.push_str("\\r"),
+            '\t' => encoded.push_str("\\t"),
+            c if c.is_control() => encoded.push_str(&format!("\\u{:04X}", c as u32)),
+            c => encoded.push(c),
+        }
+    }
+    encoded.push('"');
+    encoded
+}
+
+/// Floats always carry a `.` (or are `inf`/`nan`) so they are not read back as integers.
+fn encode_float(f: f64) -> String {
+    if f.is_nan() {
+        "nan".to_string()
+    } else if f.is_infinite() {
+        if f > 0.0 { "inf".to_string() } else { "-inf".to_string() }
+    } else {
+        let encoded = f.to_string();
+        if encoded.contains('.') { encoded } else { format!("{}.0", encoded) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_sample_toml() -> Value {
+        toml::from_str(r#"
+            title = "Say \"hi\"\n\tbye"
+            released = 2024-05-01T10:00:00Z
+            empty = []
+
+            [foo]
+            integers = [1, 2]
+            matrix = [[1.0, -2.5], []]
+            enabled = true
+            options = {}
+
+            [[products]]
+            name = "Hammer"
+            sku = 738594937
+
+            [[products]]
+            name = "Nail"
+            price = 1e3
+        "#).unwrap()
+    }
+
+    #[test]
+    fn test_flatten() {
+        let toml_value = create_sample_toml();
+
+        assert_eq!(flatten(&toml_value, "").unwrap(), vec![
+            "empty = []",
+            "foo.enabled = true",
+            "foo.integers[0] = 1",
+            "foo.integers[1] = 2",
+            "foo.matrix[0][0] = 1.0",
+            "foo.matrix[0][1] = -2.5",
+            "foo.matrix[1] = []",
+            "foo.options = {}",
+            "products[0].name = \"Hammer\"",
+            "products[0].sku = 738594937",
+            "products[1].name = \"Nail\"",
+            "products[1].price = 1000.0",
+            "released = 2024-05-01T10:00:00Z",
+            "title = \"Say \\\"hi\\\"\\n\\tbye\"",
+        ]);
+        assert_eq!(flatten(toml_value.get("products").unwrap(), "products").unwrap()[0], "products[0].name = \"Hammer\"");
+        assert_eq!(flatten(toml_value.get("foo").unwrap().get("options").unwrap(), "foo.options").unwrap(), vec!["foo.options = {}"]);
+        assert!(flatten(&Value::Table(Table::new()), "").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_flatten_quoted_keys() {
+        let toml_value: Value = toml::from_str(r#"
+            [servers]
+            "example.com" = 1
+            "a = b" = { "x[0]" = 2 }
+            "'quoted'" = 3
+            "" = 4
+        "#).unwrap();
+
+        let lines = flatten(&toml_value, "").unwrap();
+        assert_eq!(lines, vec![
+            "servers.\"\" = 4",
+            "servers.\"'quoted'\" = 3",
+            "servers.\"a = b\".\"x[0]\" = 2",
+            "servers.\"example.com\" = 1",
+        ]);
+        assert_eq!(unflatten(&lines.join("\n")).unwrap(), toml_value);
+    }
+
+    #[test]
+    fn test_unflatten_round_trip() {
+        let toml_value = create_sample_toml();
+
+        let lines = flatten(&toml_value, "").unwrap();
+        assert_eq!(unflatten(&lines.join("\n")).unwrap(), toml_value);
+
+        let mut reversed = lines.clone();
+        reversed.reverse();
+        assert_eq!(unflatten(&reversed.join("\n")).unwrap(), toml_value);
+    }
+
+    #[test]
+    fn test_unflatten_invalid() {
+        assert!(unflatten("# comment\n\nfoo.bar = 1").is_ok());
+        assert!(unflatten("foo.bar").is_err());
+        assert!(unflatten("foo.bar = 0x").is_err());
+    }
+}
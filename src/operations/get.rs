@@ -1,24 +1,78 @@
-use toml::Value;
-use serde_json;
+use toml::{Table, Value};
+use super::path::{split_first_key, split_key_part};
+use std::error::Error;
 
 pub fn get_value(toml_value: &Value, key: &str) -> String {
+    match lookup(toml_value, key) {
+        Some(value) => format_value(value),
+        None => String::new(),
+    }
+}
+
+/// Finds the value at `key`, or `None` if any part of the path is missing.
+pub fn lookup<'a>(toml_value: &'a Value, key: &str) -> Option<&'a Value> {
     let mut current = toml_value;
-    for part in key.split('.') {
-        match navigate_value(current, part) {
-            Some(value) => current = value,
-            None => return String::new(),
+    let mut rest = key;
+    loop {
+        let (part, remaining) = split_first_key(rest);
+        current = navigate_value(current, part)?;
+        if remaining.is_empty() {
+            return Some(current);
+        }
+        rest = remaining;
+    }
+}
+
+/// Collects several keys into one table. Each entry of `keys` is a path, named
+/// after its last key (`package.version` becomes `version`), or `alias=path`.
+pub fn project(toml_value: &Value, keys: &[String]) -> Result<Table, Box<dyn Error>> {
+    let mut projection = Table::new();
+    for spec in keys {
+        // A quoted key may hold `=` itself: `"a=b"` has no alias.
+        let (name, key) = match spec.split_once('=').filter(|(alias, _)| !alias.contains(['"', '\''])) {
+            Some((alias, key)) => (alias.trim().to_string(), key.trim()),
+            None => (default_name(spec), spec.as_str()),
+        };
+        let value = lookup(toml_value, key).ok_or_else(|| format!("{} does not exist", key))?;
+        if projection.insert(name.to_string(), value.clone()).is_some() {
+            return Err(format!("{} is selected twice; give one of the keys an alias (name=path)", name).into());
         }
     }
-    format_value(current)
+    Ok(projection)
+}
+
+/// Converts to JSON, writing datetimes as strings.
+pub fn to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::String(s) => serde_json::Value::from(s.as_str()),
+        Value::Integer(i) => serde_json::Value::from(*i),
+        Value::Float(f) => serde_json::Value::from(*f),
+        Value::Boolean(b) => serde_json::Value::from(*b),
+        Value::Datetime(dt) => serde_json::Value::from(dt.to_string()),
+        Value::Array(array) => serde_json::Value::Array(array.iter().map(to_json).collect()),
+        Value::Table(table) => serde_json::Value::Object(table.iter().map(|(k, v)| (k.clone(), to_json(v))).collect()),
+    }
+}
+
+/// The last key of a path without its indices: `products[1].name` gives `name`.
+fn default_name(key: &str) -> String {
+    let mut rest = key;
+    loop {
+        let (part, remaining) = split_first_key(rest);
+        if remaining.is_empty() {
+            return split_key_part(part).0.into_owned();
+        }
+        rest = remaining;
+    }
 }
 
 fn navigate_value<'a>(current: &'a Value, part: &str) -> Option<&'a Value> {
-    if let Some(bracket_pos) = part.find('[') {
-        let key = &part[..bracket_pos];
-        let array_part = &part[bracket_pos..];
-        navigate_table(current, key).and_then(|v| navigate_nested_array(v, array_part))
+    let (key, array_part) = split_key_part(part);
+    let value = navigate_table(current, &key)?;
+    if array_part.is_empty() {
+        Some(value)
     } else {
-        navigate_table(current, part)
+        navigate_nested_array(value, array_part)
     }
 }
 
@@ -135,6 +189,43 @@ mod tests {
         assert_eq!(get_value(&toml_value, "products[2].name"), "");
     }
 
+    #[test]
+    fn test_project() {
+        let toml_value = create_sample_toml();
+        let keys = |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect::<Vec<_>>();
+
+        let projection = project(&toml_value, &keys(&["foo.bar", "count=fruits.apples", "products[1].name", "foo.colors"])).unwrap();
+        assert_eq!(serde_json::to_string(&to_json(&Value::Table(projection))).unwrap(),
+            r#"{"bar":"some string","colors":["red","yellow","green"],"count":3,"name":"Nail"}"#);
+
+        assert!(project(&toml_value, &keys(&["products[0].name", "products[1].name"])).is_err());
+        assert!(project(&toml_value, &keys(&["foo.missing"])).is_err());
+    }
+
+    #[test]
+    fn test_get_quoted_keys() {
+        let toml_value: Value = toml::from_str(r#"
+            [hosts."db.example.com"]
+            ports = [5432]
+            "a=b" = 1
+            "it's" = 2
+        "#).unwrap();
+
+        assert_eq!(get_value(&toml_value, r#"hosts."db.example.com".ports[0]"#), "5432");
+        assert_eq!(get_value(&toml_value, r#"hosts.'db.example.com'."a=b""#), "1");
+        assert_eq!(get_value(&toml_value, r#"hosts."db.example.com"."it's""#), "2");
+        assert!(lookup(&toml_value, "hosts.db.example.com").is_none());
+
+        let projection = project(&toml_value, &[r#"hosts."db.example.com"."a=b""#.to_string()]).unwrap();
+        assert_eq!(projection.get("a=b"), Some(&Value::Integer(1)));
+    }
+
+    #[test]
+    fn test_to_json_datetime() {
+        let toml_value: Value = toml::from_str("released = 2024-05-01T10:00:00Z").unwrap();
+        assert_eq!(to_json(&toml_value).to_string(), r#"{"released":"2024-05-01T10:00:00Z"}"#);
+    }
+
     #[test]
     fn test_get_nonexistent_values() {
         let toml_value = create_sample_toml();
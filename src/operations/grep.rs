@@ -0,0 +1,226 @@
+//! Searching a document: every leaf is matched by its full path (as printed by
+//! `flatten`), its value and its kind.
+
+use regex::Regex;
+use toml::Value;
+use super::flatten::{encode_leaf, leaves};
+use super::inspect::type_name;
+use super::parse::ValueType;
+use std::error::Error;
+
+pub enum Pattern {
+    /// Matches anywhere in the text unless anchored with `^` / `$`.
+    Regex(Regex),
+    /// Matches the whole text; `*` stands for any run of characters, `?` for one.
+    Glob(String),
+}
+
+impl Pattern {
+    pub fn new(pattern: &str, glob: bool) -> Result<Pattern, Box<dyn Error>> {
+        if glob {
+            Ok(Pattern::Glob(pattern.to_string()))
+        } else {
+            Ok(Pattern::Regex(Regex::new(pattern).map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?))
+        }
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            Pattern::Regex(regex) => regex.is_match(text),
+            Pattern::Glob(glob) => glob_match(&glob.chars().collect::<Vec<_>>(), &text.chars().collect::<Vec<_>>()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct GrepOptions {
+    pub key: Option<Pattern>,
+    pub value: Option<Pattern>,
+    /// Kinds as returned by `kind_filter`; empty means any kind.
+    pub kinds: Vec<&'static str>,
+}
+
+/// Checks a `--kind` name, accepting the same names and aliases as `--type`
+/// plus `empty-array` and `empty-table`, the only arrays and tables that are
+/// leaves.
+pub fn kind_filter(name: &str) -> Result<&'static str, Box<dyn Error>> {
+    match name {
+        "empty-array" => Ok("empty-array"),
+        "empty-table" => Ok("empty-table"),
+        name => Ok(name.parse::<ValueType>()?.name()),
+    }
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Array(_) => "empty-array",
+        Value::Table(_) => "empty-table",
+        value => type_name(value),
+    }
+}
+
+fn is_match(pattern: &Option<Pattern>, text: &str) -> bool {
+    match pattern {
+        Some(pattern) => pattern.is_match(text),
+        None => true,
+    }
+}
+
+/// Returns `path = value` for every leaf matching all given criteria.
+pub fn grep(toml_value: &Value, options: &GrepOptions) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut matches = Vec::new();
+    for (path, value) in leaves(toml_value, "") {
+        let encoded = encode_leaf(value);
+        let text = match value {
+            Value::String(s) => s.as_str(),
+            _ => encoded.as_str(),
+        };
+        let matched = is_match(&options.key, &path)
+            && is_match(&options.value, text)
+            && (options.kinds.is_empty() || options.kinds.contains(&kind(value)));
+        if matched {
+            matches.push(format!("{} = {}", path, encoded));
+        }
+    }
+    Ok(matches)
+}
+
+/// Matches greedily, going back only to the last `*` seen: a later `*` can
+/// absorb whatever an earlier one would have, so this stays linear per star.
+fn glob_match(glob: &[char], text: &[char]) -> bool {
+    let (mut g, mut t) = (0, 0);
+    let mut star = None;
+    while t < text.len() {
+        match glob.get(g) {
+            Some('*') => {
+                star = Some((g, t));
+                g += 1;
+            },
+            Some(&c) if c == '?' || c == text[t] => {
+                g += 1;
+                t += 1;
+            },
+            _ => match star {
+                Some((star_g, star_t)) => {
+                    star = Some((star_g, star_t + 1));
+                    g = star_g + 1;
+                    t = star_t + 1;
+                },
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_sample_toml() -> Value {
+        toml::from_str(r#"
+            [database]
+            host = "localhost"
+            port = 5432
+            replica_port = 5433
+
+            [cache]
+            url = "redis://localhost:6379"
+            ttl = 1.5
+            tags = []
+
+            [[services]]
+            name = "api"
+            host = "api.internal"
+            port = 8080
+        "#).unwrap()
+    }
+
+    fn regex(pattern: &str) -> Option<Pattern> {
+        Some(Pattern::new(pattern, false).unwrap())
+    }
+
+    fn glob(pattern: &str) -> Option<Pattern> {
+        Some(Pattern::new(pattern, true).unwrap())
+    }
+
+    #[test]
+    fn test_grep_regex() {
+        let toml_value = create_sample_toml();
+
+        let options = GrepOptions { key: regex("port$"), ..Default::default() };
+        assert_eq!(grep(&toml_value, &options).unwrap(), vec![
+            "database.port = 5432",
+            "database.replica_port = 5433",
+            "services[0].port = 8080",
+        ]);
+
+        let options = GrepOptions { value: regex("localhost"), ..Default::default() };
+        assert_eq!(grep(&toml_value, &options).unwrap(), vec![
+            "cache.url = \"redis://localhost:6379\"",
+            "database.host = \"localhost\"",
+        ]);
+
+        let options = GrepOptions { key: regex("host"), value: regex("^localhost$"), ..Default::default() };
+        assert_eq!(grep(&toml_value, &options).unwrap(), vec!["database.host = \"localhost\""]);
+
+        assert!(Pattern::new("port(", false).is_err());
+    }
+
+    #[test]
+    fn test_grep_glob() {
+        let toml_value = create_sample_toml();
+
+        let options = GrepOptions { key: glob("*.port"), ..Default::default() };
+        assert_eq!(grep(&toml_value, &options).unwrap(), vec!["database.port = 5432", "services[0].port = 8080"]);
+
+        let options = GrepOptions { key: glob("services[?].*"), value: glob("api*"), ..Default::default() };
+        assert_eq!(grep(&toml_value, &options).unwrap(), vec![
+            "services[0].host = \"api.internal\"",
+            "services[0].name = \"api\"",
+        ]);
+    }
+
+    #[test]
+    fn test_grep_quotes_keys_with_dots() {
+        let toml_value: Value = toml::from_str(r#"
+            port = 80
+
+            [hosts]
+            "db.example.com" = 5432
+        "#).unwrap();
+
+        let options = GrepOptions { key: regex("^port"), ..Default::default() };
+        assert_eq!(grep(&toml_value, &options).unwrap(), vec!["port = 80"]);
+
+        let options = GrepOptions { key: glob("hosts.*"), ..Default::default() };
+        assert_eq!(grep(&toml_value, &options).unwrap(), vec![r#"hosts."db.example.com" = 5432"#]);
+    }
+
+    #[test]
+    fn test_grep_glob_backtracking() {
+        let text: Vec<char> = "a".repeat(40).chars().collect();
+        let glob: Vec<char> = "*a".repeat(20).chars().chain("b".chars()).collect();
+        assert!(!glob_match(&glob, &text));
+        assert!(glob_match(&"*a*a*".chars().collect::<Vec<_>>(), &text));
+        assert!(glob_match(&"a*?".chars().collect::<Vec<_>>(), &"ab".chars().collect::<Vec<_>>()));
+        assert!(!glob_match(&"a*?".chars().collect::<Vec<_>>(), &"a".chars().collect::<Vec<_>>()));
+    }
+
+    #[test]
+    fn test_grep_kinds() {
+        let toml_value = create_sample_toml();
+
+        let options = GrepOptions { kinds: vec![kind_filter("float").unwrap(), kind_filter("empty-array").unwrap()], ..Default::default() };
+        assert_eq!(grep(&toml_value, &options).unwrap(), vec!["cache.tags = []", "cache.ttl = 1.5"]);
+
+        let options = GrepOptions { key: regex("^database"), kinds: vec![kind_filter("int").unwrap()], ..Default::default() };
+        assert_eq!(grep(&toml_value, &options).unwrap().len(), 2);
+
+        let options = GrepOptions { kinds: vec![kind_filter("empty-table").unwrap()], ..Default::default() };
+        assert!(grep(&Value::Table(toml::Table::new()), &options).unwrap().is_empty());
+
+        assert!(kind_filter("number").is_err());
+        assert!(kind_filter("array").is_err());
+    }
+}
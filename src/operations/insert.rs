@@ -0,0 +1,103 @@
+use toml::Value;
+use super::parse::parse_value;
+use super::path::split_last_index;
+use super::set::array_at;
+use std::error::Error;
+use std::ops::Range;
+
+pub fn insert_value(toml_value: &mut Value, key: &str, value: &str) -> Result<Range<usize>, Box<dyn Error>> {
+    let spread = value.trim().starts_with("...");
+    insert_parsed_value(toml_value, key, parse_value(value)?, spread)
+}
+
+/// Inserts `value` at the position given by the last index of `key`
+/// (`ports[1]`), shifting later elements; `ports[]` appends. With `spread`
+/// the elements of `value` are inserted one after another. Returns the
+/// indices they end up at, so that the formatted document can make room for
+/// them the same way.
+pub fn insert_parsed_value(toml_value: &mut Value, key: &str, value: Value, spread: bool) -> Result<Range<usize>, Box<dyn Error>> {
+    let (array_key, index) = split_last_index(key)?;
+    let array = array_at(toml_value, array_key)?;
+    let index = index.unwrap_or(array.len());
+    if index > array.len() {
+        return Err(format!("Index {} is out of bounds for {} (length {})", index, array_key, array.len()).into());
+    }
+
+    let values = match value {
+        Value::Array(values) if spread => values,
+        _ if spread => return Err("Expected an array after spread operator".into()),
+        value => vec![value],
+    };
+    let inserted = index..index + values.len();
+    array.splice(index..index, values);
+    Ok(inserted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::get::get_value;
+
+    fn create_sample_toml() -> Value {
+        toml::from_str(r#"
+            [foo]
+            integers = [1, 2, 3]
+            matrix = [[1, 2], [3, 4]]
+
+            [[products]]
+            name = "Hammer"
+
+            [[products]]
+            name = "Nail"
+        "#).unwrap()
+    }
+
+    #[test]
+    fn test_insert_values() {
+        let mut toml_value = create_sample_toml();
+
+        insert_value(&mut toml_value, "foo.integers[1]", "5").unwrap();
+        assert_eq!(get_value(&toml_value, "foo.integers"), "[1,5,2,3]");
+
+        insert_value(&mut toml_value, "foo.integers[0]", "0").unwrap();
+        assert_eq!(get_value(&toml_value, "foo.integers"), "[0,1,5,2,3]");
+
+        insert_value(&mut toml_value, "foo.integers[5]", "9").unwrap();
+        assert_eq!(get_value(&toml_value, "foo.integers"), "[0,1,5,2,3,9]");
+
+        assert!(insert_value(&mut toml_value, "foo.integers[10]", "9").is_err());
+        assert!(insert_value(&mut toml_value, "foo.integers", "9").is_err());
+    }
+
+    #[test]
+    fn test_insert_with_spread() {
+        let mut toml_value = create_sample_toml();
+
+        assert_eq!(insert_value(&mut toml_value, "foo.integers[1]", "...[7, 8]").unwrap(), 1..3);
+        assert_eq!(get_value(&toml_value, "foo.integers"), "[1,7,8,2,3]");
+
+        insert_value(&mut toml_value, "new_array[0]", "...[1, 2]").unwrap();
+        assert_eq!(get_value(&toml_value, "new_array"), "[1,2]");
+    }
+
+    #[test]
+    fn test_insert_nested_arrays() {
+        let mut toml_value = create_sample_toml();
+
+        insert_value(&mut toml_value, "foo.matrix[1][0]", "9").unwrap();
+        assert_eq!(get_value(&toml_value, "foo.matrix"), "[[1,2],[9,3,4]]");
+
+        insert_value(&mut toml_value, "foo.matrix[1]", "[5, 6]").unwrap();
+        assert_eq!(get_value(&toml_value, "foo.matrix"), "[[1,2],[5,6],[9,3,4]]");
+    }
+
+    #[test]
+    fn test_insert_array_of_tables() {
+        let mut toml_value = create_sample_toml();
+
+        insert_value(&mut toml_value, "products[1]", "{name: \"Screw\"}").unwrap();
+        assert_eq!(get_value(&toml_value, "products[0].name"), "Hammer");
+        assert_eq!(get_value(&toml_value, "products[1].name"), "Screw");
+        assert_eq!(get_value(&toml_value, "products[2].name"), "Nail");
+    }
+}
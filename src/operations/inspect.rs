@@ -0,0 +1,138 @@
+//! Structural questions about a key: whether it exists, its TOML type, its
+//! length and the keys of a table. An empty key refers to the whole document.
+
+use toml::Value;
+use super::get::lookup;
+use std::error::Error;
+
+pub fn exists(toml_value: &Value, key: &str) -> bool {
+    find(toml_value, key).is_some()
+}
+
+/// The TOML type at `key`, using the type names accepted by `--type` plus
+/// `array` and `table`.
+pub fn type_of(toml_value: &Value, key: &str) -> Result<&'static str, Box<dyn Error>> {
+    Ok(type_name(existing(toml_value, key)?))
+}
+
+/// The number of elements of an array, entries of a table or characters of a string.
+pub fn length(toml_value: &Value, key: &str) -> Result<usize, Box<dyn Error>> {
+    match existing(toml_value, key)? {
+        Value::Array(array) => Ok(array.len()),
+        Value::Table(table) => Ok(table.len()),
+        Value::String(s) => Ok(s.chars().count()),
+        value => Err(format!("{} is {} {}, which has no length", key, article(type_name(value)), type_name(value)).into()),
+    }
+}
+
+pub fn keys(toml_value: &Value, key: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    match existing(toml_value, key)? {
+        Value::Table(table) => Ok(table.keys().cloned().collect()),
+        value => Err(format!("{} is {} {}, not a table", key, article(type_name(value)), type_name(value)).into()),
+    }
+}
+
+pub fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::String(_) => "string",
+        Value::Integer(_) => "integer",
+        Value::Float(_) => "float",
+        Value::Boolean(_) => "boolean",
+        Value::Datetime(dt) => match (dt.date, dt.time, dt.offset) {
+            (Some(_), Some(_), Some(_)) => "datetime",
+            (Some(_), Some(_), None) => "datetime-local",
+            (Some(_), None, _) => "date",
+            _ => "time",
+        },
+        Value::Array(_) => "array",
+        Value::Table(_) => "table",
+    }
+}
+
+fn find<'a>(toml_value: &'a Value, key: &str) -> Option<&'a Value> {
+    if key.is_empty() {
+        Some(toml_value)
+    } else {
+        lookup(toml_value, key)
+    }
+}
+
+fn existing<'a>(toml_value: &'a Value, key: &str) -> Result<&'a Value, Box<dyn Error>> {
+    find(toml_value, key).ok_or_else(|| format!("{} does not exist", key).into())
+}
+
+fn article(name: &str) -> &'static str {
+    if name.starts_with(['a', 'e', 'i', 'o', 'u']) { "an" } else { "a" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_sample_toml() -> Value {
+        toml::from_str(r#"
+            title = "Café"
+
+            [foo]
+            bar = "some string"
+            ratio = 0.5
+            enabled = false
+            colors = ["red", "yellow", "green"]
+
+            [times]
+            offset = 2024-05-01T10:00:00Z
+            local = 2024-05-01T10:00:00
+            day = 2024-05-01
+            hour = 10:00:00
+
+            [[products]]
+            name = "Hammer"
+            sku = 738594937
+        "#).unwrap()
+    }
+
+    #[test]
+    fn test_exists() {
+        let toml_value = create_sample_toml();
+
+        assert!(exists(&toml_value, "foo.bar"));
+        assert!(exists(&toml_value, "foo.enabled"));
+        assert!(exists(&toml_value, "products[0].sku"));
+        assert!(exists(&toml_value, ""));
+        assert!(!exists(&toml_value, "foo.baz"));
+        assert!(!exists(&toml_value, "foo.colors[3]"));
+    }
+
+    #[test]
+    fn test_type_of() {
+        let toml_value = create_sample_toml();
+
+        assert_eq!(type_of(&toml_value, "foo.bar").unwrap(), "string");
+        assert_eq!(type_of(&toml_value, "foo.ratio").unwrap(), "float");
+        assert_eq!(type_of(&toml_value, "foo.enabled").unwrap(), "boolean");
+        assert_eq!(type_of(&toml_value, "products[0].sku").unwrap(), "integer");
+        assert_eq!(type_of(&toml_value, "times.offset").unwrap(), "datetime");
+        assert_eq!(type_of(&toml_value, "times.local").unwrap(), "datetime-local");
+        assert_eq!(type_of(&toml_value, "times.day").unwrap(), "date");
+        assert_eq!(type_of(&toml_value, "times.hour").unwrap(), "time");
+        assert_eq!(type_of(&toml_value, "products").unwrap(), "array");
+        assert_eq!(type_of(&toml_value, "foo").unwrap(), "table");
+        assert!(type_of(&toml_value, "missing").is_err());
+    }
+
+    #[test]
+    fn test_length_and_keys() {
+        let toml_value = create_sample_toml();
+
+        assert_eq!(length(&toml_value, "foo.colors").unwrap(), 3);
+        assert_eq!(length(&toml_value, "products").unwrap(), 1);
+        assert_eq!(length(&toml_value, "times").unwrap(), 4);
+        assert_eq!(length(&toml_value, "title").unwrap(), 4);
+        assert!(length(&toml_value, "foo.ratio").is_err());
+        assert!(length(&toml_value, "missing").is_err());
+
+        assert_eq!(keys(&toml_value, "products[0]").unwrap(), vec!["name", "sku"]);
+        assert_eq!(keys(&toml_value, "").unwrap(), vec!["foo", "products", "times", "title"]);
+        assert!(keys(&toml_value, "foo.colors").is_err());
+    }
+}
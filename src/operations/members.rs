@@ -0,0 +1,143 @@
+//! Set-like operations on arrays, where elements are found by value rather
+//! than by index. Values are compared after parsing, so `1`, `0x01` and `0b1`
+//! all refer to the same integer element.
+
+use toml::Value;
+use super::get::lookup;
+use super::parse::parse_value;
+use super::path::array_mut;
+use super::set::array_at;
+use std::error::Error;
+
+pub fn add_unique_value(toml_value: &mut Value, key: &str, value: &str) -> Result<usize, Box<dyn Error>> {
+    let spread = value.trim().starts_with("...");
+    add_unique_parsed_value(toml_value, key, parse_value(value)?, spread)
+}
+
+/// Appends `value` to the array at `key` unless it is already there, creating
+/// the array if needed. With `spread` each element of `value` is added on its
+/// own. Returns how many elements were added.
+pub fn add_unique_parsed_value(toml_value: &mut Value, key: &str, value: Value, spread: bool) -> Result<usize, Box<dyn Error>> {
+    let array = array_at(toml_value, key)?;
+    let mut added = 0;
+    for value in spread_values(value, spread)? {
+        if !array.contains(&value) {
+            array.push(value);
+            added += 1;
+        }
+    }
+    Ok(added)
+}
+
+pub fn remove_array_value(toml_value: &mut Value, key: &str, value: &str) -> Result<usize, Box<dyn Error>> {
+    let spread = value.trim().starts_with("...");
+    remove_array_parsed_value(toml_value, key, parse_value(value)?, spread)
+}
+
+/// Removes every element equal to `value` from the array at `key` and returns
+/// how many were removed. With `spread` each element of `value` is removed.
+pub fn remove_array_parsed_value(toml_value: &mut Value, key: &str, value: Value, spread: bool) -> Result<usize, Box<dyn Error>> {
+    let values = spread_values(value, spread)?;
+    let array = array_mut(toml_value, key)?;
+    let length = array.len();
+    array.retain(|element| !values.contains(element));
+    Ok(length - array.len())
+}
+
+/// Whether the array at `key` contains `value`; a missing array contains nothing.
+pub fn contains_value(toml_value: &Value, key: &str, value: &str) -> Result<bool, Box<dyn Error>> {
+    Ok(index_of(toml_value, key, value)?.is_some())
+}
+
+pub fn contains_parsed_value(toml_value: &Value, key: &str, value: &Value) -> Result<bool, Box<dyn Error>> {
+    Ok(index_of_parsed_value(toml_value, key, value)?.is_some())
+}
+
+pub fn index_of(toml_value: &Value, key: &str, value: &str) -> Result<Option<usize>, Box<dyn Error>> {
+    index_of_parsed_value(toml_value, key, &parse_value(value)?)
+}
+
+/// The index of the first element equal to `value` in the array at `key`.
+pub fn index_of_parsed_value(toml_value: &Value, key: &str, value: &Value) -> Result<Option<usize>, Box<dyn Error>> {
+    match lookup(toml_value, key) {
+        Some(Value::Array(array)) => Ok(array.iter().position(|element| element == value)),
+        Some(_) => Err(format!("{} is not an array", key).into()),
+        None => Ok(None),
+    }
+}
+
+fn spread_values(value: Value, spread: bool) -> Result<Vec<Value>, Box<dyn Error>> {
+    match value {
+        Value::Array(values) if spread => Ok(values),
+        _ if spread => Err("Expected an array after spread operator".into()),
+        value => Ok(vec![value]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::get::get_value;
+
+    fn create_sample_toml() -> Value {
+        toml::from_str(r#"
+            [foo]
+            features = ["tls", "http2"]
+            integers = [1, 2, 3, 2]
+            pairs = [[1, 2], [3, 4]]
+        "#).unwrap()
+    }
+
+    #[test]
+    fn test_add_unique() {
+        let mut toml_value = create_sample_toml();
+
+        add_unique_value(&mut toml_value, "foo.features", "tls").unwrap();
+        assert_eq!(get_value(&toml_value, "foo.features"), r#"["tls","http2"]"#);
+
+        add_unique_value(&mut toml_value, "foo.features", "...[http2, json, json]").unwrap();
+        assert_eq!(get_value(&toml_value, "foo.features"), r#"["tls","http2","json"]"#);
+
+        add_unique_value(&mut toml_value, "foo.pairs", "[3, 4]").unwrap();
+        assert_eq!(get_value(&toml_value, "foo.pairs"), "[[1,2],[3,4]]");
+
+        add_unique_value(&mut toml_value, "new.features", "tls").unwrap();
+        assert_eq!(get_value(&toml_value, "new.features"), r#"["tls"]"#);
+    }
+
+    #[test]
+    fn test_remove_array_value() {
+        let mut toml_value = create_sample_toml();
+
+        assert_eq!(remove_array_value(&mut toml_value, "foo.integers", "0x2").unwrap(), 2);
+        assert_eq!(get_value(&toml_value, "foo.integers"), "[1,3]");
+
+        assert_eq!(remove_array_value(&mut toml_value, "foo.features", "...[tls, gzip]").unwrap(), 1);
+        assert_eq!(get_value(&toml_value, "foo.features"), r#"["http2"]"#);
+
+        assert_eq!(remove_array_value(&mut toml_value, "foo.integers", "\"1\"").unwrap(), 0);
+        assert!(remove_array_value(&mut toml_value, "missing", "1").is_err());
+
+        let texts = Value::Array(vec![Value::String("http2".to_string())]);
+        assert_eq!(remove_array_parsed_value(&mut toml_value, "foo.features", texts, true).unwrap(), 1);
+        assert_eq!(get_value(&toml_value, "foo.features"), "[]");
+    }
+
+    #[test]
+    fn test_contains_and_index_of() {
+        let toml_value = create_sample_toml();
+
+        assert!(contains_value(&toml_value, "foo.features", "http2").unwrap());
+        assert!(!contains_value(&toml_value, "foo.features", "gzip").unwrap());
+        assert!(!contains_value(&toml_value, "missing", "gzip").unwrap());
+        assert!(contains_value(&toml_value, "foo.features[0]", "tls").is_err());
+
+        assert_eq!(index_of(&toml_value, "foo.integers", "2").unwrap(), Some(1));
+        assert_eq!(index_of(&toml_value, "foo.integers", "2.0").unwrap(), None);
+        assert_eq!(index_of(&toml_value, "foo.pairs", "[3, 4]").unwrap(), Some(1));
+
+        let text = Value::String("2".to_string());
+        assert!(!contains_parsed_value(&toml_value, "foo.integers", &text).unwrap());
+        assert_eq!(index_of_parsed_value(&toml_value, "foo.integers", &Value::Integer(3)).unwrap(), Some(2));
+    }
+}
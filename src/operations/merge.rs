@@ -0,0 +1,179 @@
+//! Three-way merge of documents at the key level. Tables are merged key by key;
+//! every other value, arrays included, is taken as a whole from the side that
+//! changed it. A key changed differently on both sides is a conflict.
+
+use toml::{Table, Value};
+use super::canonical::same;
+
+#[derive(Debug, PartialEq)]
+pub struct Conflict {
+    pub path: String,
+    /// The value on each side, `None` where that side removed the key.
+    pub ours: Option<Value>,
+    pub theirs: Option<Value>,
+}
+
+/// Merges `ours` and `theirs`, both derived from `base`. In the result each
+/// conflicting key holds the string `conflict_marker(i)`, where `i` is the
+/// position of the conflict in the returned list, so it can be found again
+/// once the document is written out.
+pub fn merge3(base: &Value, ours: &Value, theirs: &Value) -> (Value, Vec<Conflict>) {
+    let mut conflicts = Vec::new();
+    let merged = merge_tables(base.as_table(), ours.as_table(), theirs.as_table(), "", &mut conflicts);
+    (Value::Table(merged), conflicts)
+}
+
+pub fn conflict_marker(index: usize) -> String {
+    format!("<<ctoml-merge-conflict-{}>>", index)
+}
+
+fn merge_tables(base: Option<&Table>, ours: Option<&Table>, theirs: Option<&Table>, path: &str, conflicts: &mut Vec<Conflict>) -> Table {
+    let mut keys: Vec<&String> = ours.into_iter().chain(theirs).flat_map(|table| table.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    let mut merged = Table::new();
+    for key in keys {
+        let path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+        let [base, ours, theirs] = [base, ours, theirs].map(|table| table.and_then(|table| table.get(key)));
+        if let Some(value) = merge_values(base, ours, theirs, &path, conflicts) {
+            merged.insert(key.clone(), value);
+        }
+    }
+    merged
+}
+
+fn merge_values(base: Option<&Value>, ours: Option<&Value>, theirs: Option<&Value>, path: &str, conflicts: &mut Vec<Conflict>) -> Option<Value> {
+    let same = |a: Option<&Value>, b: Option<&Value>| match (a, b) {
+        (Some(a), Some(b)) => same(a, b),
+        (a, b) => a.is_none() && b.is_none(),
+    };
+    if same(ours, theirs) || same(theirs, base) {
+        return ours.cloned();
+    }
+    if same(ours, base) {
+        return theirs.cloned();
+    }
+    if let (Some(Value::Table(ours)), Some(Value::Table(theirs))) = (ours, theirs) {
+        let base = base.and_then(Value::as_table);
+        return Some(Value::Table(merge_tables(base, Some(ours), Some(theirs), path, conflicts)));
+    }
+
+    conflicts.push(Conflict { path: path.to_string(), ours: ours.cloned(), theirs: theirs.cloned() });
+    Some(Value::String(conflict_marker(conflicts.len() - 1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::get::get_value;
+
+    fn parse(content: &str) -> Value {
+        toml::from_str(content).unwrap()
+    }
+
+    fn create_base() -> Value {
+        parse(r#"
+            [package]
+            name = "ctoml"
+            version = "0.1.0"
+            keywords = ["toml"]
+
+            [dependencies]
+            toml = "0.8"
+            serde_json = "1.0"
+        "#)
+    }
+
+    #[test]
+    fn test_merge_unrelated_changes() {
+        let base = create_base();
+        let ours = parse(r#"
+            [package]
+            name = "ctoml"
+            version = "0.2.0"
+            keywords = ["toml"]
+
+            [dependencies]
+            toml = "0.8"
+        "#);
+        let theirs = parse(r#"
+            [package]
+            name = "ctoml"
+            version = "0.1.0"
+            keywords = ["toml", "cli"]
+
+            [dependencies]
+            toml = "0.8"
+            serde_json = "1.0"
+            regex = "1"
+        "#);
+
+        let (merged, conflicts) = merge3(&base, &ours, &theirs);
+        assert!(conflicts.is_empty());
+        assert_eq!(get_value(&merged, "package.version"), "0.2.0");
+        assert_eq!(get_value(&merged, "package.keywords"), r#"["toml","cli"]"#);
+        assert_eq!(get_value(&merged, "dependencies.regex"), "1");
+        assert_eq!(get_value(&merged, "dependencies.serde_json"), "");
+    }
+
+    #[test]
+    fn test_merge_conflicts() {
+        let base = create_base();
+        let ours = parse(r#"
+            [package]
+            name = "ctoml"
+            version = "0.2.0"
+            keywords = ["toml"]
+        "#);
+        let theirs = parse(r#"
+            [package]
+            name = "ctoml"
+            version = "0.3.0"
+            keywords = ["toml"]
+
+            [dependencies]
+            toml = "0.9"
+            serde_json = "1.0"
+        "#);
+
+        let (merged, conflicts) = merge3(&base, &ours, &theirs);
+        assert_eq!(conflicts, vec![
+            Conflict { path: "dependencies".to_string(), ours: None, theirs: theirs.get("dependencies").cloned() },
+            Conflict { path: "package.version".to_string(), ours: Some(parse("v = '0.2.0'")["v"].clone()), theirs: Some(parse("v = '0.3.0'")["v"].clone()) },
+        ]);
+        assert_eq!(get_value(&merged, "dependencies"), conflict_marker(0));
+        assert_eq!(get_value(&merged, "package.version"), conflict_marker(1));
+        assert_eq!(get_value(&merged, "package.name"), "ctoml");
+    }
+
+    #[test]
+    fn test_merge_same_change_and_no_base() {
+        let empty = Value::Table(Table::new());
+        let ours = parse("a = 1\n[t]\nx = 1");
+        let theirs = parse("a = 1\n[t]\ny = 2");
+
+        let (merged, conflicts) = merge3(&empty, &ours, &theirs);
+        assert!(conflicts.is_empty());
+        assert_eq!(get_value(&merged, "t"), r#"{"x":1,"y":2}"#);
+    }
+
+    #[test]
+    fn test_merge_nan() {
+        let base = parse("limit = nan
+weights = [1.0, nan]
+name = 'a'");
+        let ours = parse("limit = nan
+weights = [1.0, nan]
+name = 'b'");
+        let theirs = parse("limit = nan
+weights = [2.0, nan]
+name = 'a'");
+
+        let (merged, conflicts) = merge3(&base, &ours, &theirs);
+        assert!(conflicts.is_empty());
+        assert!(merged["limit"].as_float().unwrap().is_nan());
+        assert_eq!(merged["weights"][0], Value::Float(2.0));
+        assert_eq!(get_value(&merged, "name"), "b");
+    }
+}
@@ -1,24 +1,39 @@
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 use toml::Value;
+use toml::value::{Date, Datetime, Offset, Time};
 
 pub fn parse_value(value: &str) -> Result<Value, Box<dyn std::error::Error>> {
     let trimmed = value.trim();
     
-    if trimmed.starts_with("...") {
-        return parse_array(&trimmed[3..]);
+    if let Some(spread) = trimmed.strip_prefix("...") {
+        return parse_array(spread);
     }
 
-    if let Ok(v) = trimmed.parse::<i64>() {
-        return Ok(Value::Integer(v));
+    if is_quoted(trimmed) {
+        return parse_string(trimmed);
     }
 
-    if let Ok(v) = trimmed.parse::<f64>() {
-        return Ok(Value::Float(v));
+    if let Some(v) = parse_integer(trimmed) {
+        return Ok(Value::Integer(v?));
+    }
+
+    if let Some(v) = parse_float(trimmed) {
+        return Ok(Value::Float(v?));
     }
 
     if let Ok(v) = trimmed.parse::<bool>() {
         return Ok(Value::Boolean(v));
     }
 
+    if trimmed == "now" {
+        return Ok(Value::Datetime(now()));
+    }
+
+    if let Ok(v) = trimmed.parse::<Datetime>() {
+        return Ok(Value::Datetime(v));
+    }
+
     if trimmed.starts_with('{') && trimmed.ends_with('}') {
         return parse_table(trimmed);
     }
@@ -27,11 +42,284 @@ pub fn parse_value(value: &str) -> Result<Value, Box<dyn std::error::Error>> {
         return parse_array(trimmed);
     }
 
-    if trimmed.starts_with('"') && trimmed.ends_with('"') {
-        return Ok(Value::String(trimmed[1..trimmed.len()-1].to_string()));
+    Ok(Value::String(trimmed.to_string()))
+}
+
+/// Parses TOML integers: decimal with optional sign, `0x`, `0o` and `0b`
+/// prefixes, and underscores between digits. Returns `None` when `value` does
+/// not look like an integer at all.
+fn parse_integer(value: &str) -> Option<Result<i64, Box<dyn std::error::Error>>> {
+    let (radix, sign, digits) = if let Some(hex) = value.strip_prefix("0x") {
+        (16, "", hex)
+    } else if let Some(octal) = value.strip_prefix("0o") {
+        (8, "", octal)
+    } else if let Some(binary) = value.strip_prefix("0b") {
+        (2, "", binary)
+    } else {
+        let unsigned = value.trim_start_matches(['+', '-']);
+        if value.len() - unsigned.len() > 1 || !unsigned.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        (10, &value[..value.len() - unsigned.len()], unsigned)
+    };
+
+    if radix == 10 && !digits.chars().all(|c| c.is_ascii_digit() || c == '_') {
+        return None;
+    }
+
+    let invalid = |reason: &str| Some(Err(format!("Invalid integer {}: {}", value, reason).into()));
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix) || c == '_') {
+        return invalid("unexpected digits for the number base");
+    }
+    if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+        return invalid("underscores must be placed between digits");
+    }
+    let digits = digits.replace('_', "");
+    if radix == 10 && digits.len() > 1 && digits.starts_with('0') {
+        return invalid("leading zeros are not allowed (use the 0o prefix for octal, or quote the value)");
     }
 
-    Ok(Value::String(trimmed.to_string()))
+    match i64::from_str_radix(&format!("{}{}", sign, digits), radix) {
+        Ok(v) => Some(Ok(v)),
+        Err(_) => invalid("out of range for a 64-bit signed integer (quote the value to store it as a string)"),
+    }
+}
+
+/// Parses TOML floats, including `inf`/`nan` with optional signs and
+/// underscores between digits. Spellings that Rust would accept but TOML does
+/// not (`.5`, `5.`, `NaN`, `Infinity`, ...) are rejected rather than
+/// silently stored as strings. Returns `None` when `value` is not float-like.
+fn parse_float(value: &str) -> Option<Result<f64, Box<dyn std::error::Error>>> {
+    match check_float(value) {
+        Ok(()) => {
+            let unsigned = value.trim_start_matches(['+', '-']);
+            let magnitude = match unsigned {
+                "inf" => f64::INFINITY,
+                "nan" => f64::NAN,
+                _ => unsigned.replace('_', "").parse().ok()?,
+            };
+            Some(Ok(if value.starts_with('-') { -magnitude } else { magnitude }))
+        },
+        Err(_) if value.replace('_', "").parse::<f64>().is_err() => None,
+        Err(reason) => Some(Err(format!("Invalid float {}: {} (quote the value to store it as a string)", value, reason).into())),
+    }
+}
+
+fn check_float(value: &str) -> Result<(), &'static str> {
+    let unsigned = value.strip_prefix(['+', '-']).unwrap_or(value);
+    if unsigned == "inf" || unsigned == "nan" {
+        return Ok(());
+    }
+    if ["inf", "infinity", "nan"].iter().any(|special| unsigned.eq_ignore_ascii_case(special)) {
+        return Err("special values are written inf or nan, in lowercase, with an optional sign");
+    }
+
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(i) => (&unsigned[..i], Some(&unsigned[i + 1..])),
+        None => (unsigned, None),
+    };
+    let (integer, fraction) = match mantissa.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (mantissa, None),
+    };
+
+    if !is_digit_run(integer) {
+        return Err("digits are required before the decimal point, with underscores only between digits");
+    }
+    if integer.len() > 1 && integer.starts_with('0') {
+        return Err("leading zeros are not allowed");
+    }
+    if fraction.is_some_and(|fraction| !is_digit_run(fraction)) {
+        return Err("digits are required after the decimal point, with underscores only between digits");
+    }
+    if exponent.is_some_and(|exponent| !is_digit_run(exponent.strip_prefix(['+', '-']).unwrap_or(exponent))) {
+        return Err("the exponent needs digits, with underscores only between digits");
+    }
+    if fraction.is_none() && exponent.is_none() {
+        return Err("a fractional part or an exponent is required");
+    }
+    Ok(())
+}
+
+fn is_digit_run(digits: &str) -> bool {
+    !digits.is_empty()
+        && digits.chars().all(|c| c.is_ascii_digit() || c == '_')
+        && !digits.starts_with('_')
+        && !digits.ends_with('_')
+        && !digits.contains("__")
+}
+
+/// The TOML type a value is forced into with `--type`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueType {
+    String,
+    Integer,
+    Float,
+    Boolean,
+    Datetime,
+    LocalDatetime,
+    Date,
+    Time,
+}
+
+impl FromStr for ValueType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "string" => Ok(ValueType::String),
+            "integer" | "int" => Ok(ValueType::Integer),
+            "float" => Ok(ValueType::Float),
+            "boolean" | "bool" => Ok(ValueType::Boolean),
+            "datetime" => Ok(ValueType::Datetime),
+            "datetime-local" => Ok(ValueType::LocalDatetime),
+            "date" => Ok(ValueType::Date),
+            "time" => Ok(ValueType::Time),
+            _ => Err(format!("Unknown type '{}' (expected string, integer, float, boolean, datetime, datetime-local, date or time)", s)),
+        }
+    }
+}
+
+impl ValueType {
+    pub fn name(self) -> &'static str {
+        match self {
+            ValueType::String => "string",
+            ValueType::Integer => "integer",
+            ValueType::Float => "float",
+            ValueType::Boolean => "boolean",
+            ValueType::Datetime => "datetime",
+            ValueType::LocalDatetime => "datetime-local",
+            ValueType::Date => "date",
+            ValueType::Time => "time",
+        }
+    }
+}
+
+/// Parses `value` as the given type instead of guessing it. Strings are taken
+/// verbatim; `now` is accepted by all datetime types.
+pub fn parse_typed_value(value: &str, value_type: ValueType) -> Result<Value, Box<dyn std::error::Error>> {
+    let trimmed = value.trim();
+    let invalid = || format!("'{}' is not a valid {}", trimmed, value_type.name());
+
+    match value_type {
+        ValueType::String => Ok(Value::String(value.to_string())),
+        ValueType::Integer => match parse_value(trimmed)? {
+            Value::Integer(i) => Ok(Value::Integer(i)),
+            _ => Err(invalid().into()),
+        },
+        ValueType::Float => match parse_value(trimmed)? {
+            Value::Float(f) => Ok(Value::Float(f)),
+            Value::Integer(i) => Ok(Value::Float(i as f64)),
+            _ => Err(invalid().into()),
+        },
+        ValueType::Boolean => trimmed.parse::<bool>().map(Value::Boolean).map_err(|_| invalid().into()),
+        ValueType::Datetime | ValueType::LocalDatetime | ValueType::Date | ValueType::Time => {
+            let mut datetime = if trimmed == "now" {
+                now()
+            } else {
+                trimmed.parse::<Datetime>().map_err(|_| invalid())?
+            };
+            if trimmed == "now" {
+                match value_type {
+                    ValueType::LocalDatetime => datetime.offset = None,
+                    ValueType::Date => { datetime.time = None; datetime.offset = None; },
+                    ValueType::Time => { datetime.date = None; datetime.offset = None; },
+                    _ => {}
+                }
+            }
+            let matches = match value_type {
+                ValueType::Datetime => datetime.date.is_some() && datetime.time.is_some() && datetime.offset.is_some(),
+                ValueType::LocalDatetime => datetime.date.is_some() && datetime.time.is_some() && datetime.offset.is_none(),
+                ValueType::Date => datetime.date.is_some() && datetime.time.is_none(),
+                _ => datetime.date.is_none(),
+            };
+            if matches {
+                Ok(Value::Datetime(datetime))
+            } else {
+                Err(invalid().into())
+            }
+        },
+    }
+}
+
+/// Parses a whole file or stdin buffer as a single TOML value (`[1, 2]`,
+/// `"text"`, `{ a = 1 }`), a TOML document or a JSON value, in that order.
+pub fn parse_content(content: &str) -> Result<Value, Box<dyn std::error::Error>> {
+    if let Ok(mut document) = format!("value = {}", content.trim()).parse::<toml::Table>() {
+        if document.len() == 1 {
+            if let Some(value) = document.remove("value") {
+                return Ok(value);
+            }
+        }
+    }
+
+    if let Ok(document) = content.parse::<toml::Table>() {
+        return Ok(Value::Table(document));
+    }
+
+    match serde_json::from_str::<serde_json::Value>(content) {
+        Ok(json) => Value::try_from(json).map_err(|e| format!("Cannot represent JSON value in TOML: {}", e).into()),
+        Err(e) => Err(format!("Content is neither a TOML nor a JSON value: {}", e).into()),
+    }
+}
+
+/// The current time as an offset datetime in UTC, with second precision.
+fn now() -> Datetime {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days as i64 + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    Datetime {
+        date: Some(Date { year: year as u16, month: month as u8, day: day as u8 }),
+        time: Some(Time {
+            hour: (rem / 3_600) as u8,
+            minute: (rem % 3_600 / 60) as u8,
+            second: (rem % 60) as u8,
+            nanosecond: 0,
+        }),
+        offset: Some(Offset::Z),
+    }
+}
+
+/// The integers written in `value`, as `(path, literal)` pairs with paths
+/// relative to `value`: `""` for `value` itself, `.KEY` for the fields of a
+/// table and `[INDEX]` for the elements of an array. Quoted keys and spread
+/// arrays are left out.
+pub fn integer_literals(value: &str) -> Vec<(String, String)> {
+    let trimmed = value.trim();
+    if let Some(Ok(_)) = parse_integer(trimmed) {
+        return vec![(String::new(), trimmed.to_string())];
+    }
+    let mut literals = Vec::new();
+    if trimmed.len() >= 2 && trimmed.starts_with('{') && trimmed.ends_with('}') {
+        for pair in split_top_level(&trimmed[1..trimmed.len() - 1]).unwrap_or_default() {
+            let Some((key, val)) = pair.split_once(':') else { continue };
+            let key = key.trim();
+            if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                continue;
+            }
+            for (path, literal) in integer_literals(val) {
+                literals.push((format!(".{}{}", key, path), literal));
+            }
+        }
+    } else if trimmed.len() >= 2 && trimmed.starts_with('[') && trimmed.ends_with(']') {
+        for (index, element) in split_top_level(&trimmed[1..trimmed.len() - 1]).unwrap_or_default().into_iter().enumerate() {
+            for (path, literal) in integer_literals(element) {
+                literals.push((format!("[{}]{}", index, path), literal));
+            }
+        }
+    }
+    literals
 }
 
 fn parse_table(value: &str) -> Result<Value, Box<dyn std::error::Error>> {
@@ -42,20 +330,21 @@ fn parse_table(value: &str) -> Result<Value, Box<dyn std::error::Error>> {
         return Ok(Value::Table(table));
     }
 
-    for pair in inner.split(',') {
+    for pair in split_top_level(inner)? {
         let mut kv = pair.splitn(2, ':');
         let key = kv.next().ok_or("Missing key")?.trim();
         let val = kv.next().ok_or("Missing value")?.trim();
-        table.insert(key.to_string(), parse_value(val)?);
+        let key = if is_quoted(key) {
+            parse_string(key)?.as_str().unwrap_or(key).to_string()
+        } else {
+            key.to_string()
+        };
+        table.insert(key, parse_value(val)?);
     }
     Ok(Value::Table(table))
 }
 
 fn parse_array(value: &str) -> Result<Value, Box<dyn std::error::Error>> {
-    let mut result = Vec::new();
-    let mut depth = 0;
-    let mut current = String::new();
-
     let inner = value.trim();
 
     if !inner.starts_with('[') || !inner.ends_with(']') {
@@ -63,43 +352,113 @@ fn parse_array(value: &str) -> Result<Value, Box<dyn std::error::Error>> {
     }
 
     let inner = &inner[1..inner.len()-1];
-    
-    if inner.trim().is_empty() {
-        return Ok(Value::Array(result));
-    }
+    let result = split_top_level(inner)?
+        .into_iter()
+        .map(parse_value)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Value::Array(result))
+}
+
+/// Splits the inside of an array or inline table on commas that are neither
+/// nested in brackets nor part of a string. A quote only starts a string at
+/// the start of an element, key or value, so `O'Brien` is plain text. Empty
+/// elements are dropped.
+fn split_top_level(inner: &str) -> Result<Vec<&str>, Box<dyn std::error::Error>> {
+    let bytes = inner.as_bytes();
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut i = 0;
+    let mut at_start = true;
 
-    for c in inner.chars() {
-        match c {
-            '[' => {
+    while i < bytes.len() {
+        let was_at_start = std::mem::replace(&mut at_start, false);
+        match bytes[i] {
+            b'"' | b'\'' if was_at_start => {
+                i = string_end(inner, i).ok_or("Unterminated string")?;
+                continue;
+            }
+            b' ' | b'\t' | b'\n' | b'\r' => at_start = was_at_start,
+            b':' => at_start = true,
+            b'[' | b'{' => {
                 depth += 1;
-                current.push(c);
+                at_start = true;
             }
-            ']' => {
+            b']' | b'}' => {
                 if depth == 0 {
                     return Err("Unbalanced brackets in array".into());
                 }
                 depth -= 1;
-                current.push(c);
             }
-            ',' if depth == 0 => {
-                if !current.is_empty() {
-                    result.push(parse_value(current.trim())?);
-                    current.clear();
-                }
+            b',' if depth == 0 => {
+                parts.push(&inner[start..i]);
+                start = i + 1;
+                at_start = true;
             }
-            _ => current.push(c),
+            b',' => at_start = true,
+            _ => {}
         }
+        i += 1;
     }
 
     if depth != 0 {
         return Err("Unbalanced brackets in array".into());
     }
+    parts.push(&inner[start..]);
+
+    Ok(parts.into_iter().map(str::trim).filter(|part| !part.is_empty()).collect())
+}
+
+/// Returns the byte offset just past the string starting at `start`, which
+/// must point at a `"` or `'`.
+fn string_end(s: &str, start: usize) -> Option<usize> {
+    let rest = &s[start..];
+    let delimiter = if rest.starts_with("\"\"\"") || rest.starts_with("'''") {
+        &rest[..3]
+    } else {
+        &rest[..1]
+    };
+    let escapes = delimiter.starts_with('"');
 
-    if !current.is_empty() {
-        result.push(parse_value(current.trim())?);
+    let bytes = rest.as_bytes();
+    let mut i = delimiter.len();
+    while i < bytes.len() {
+        if escapes && bytes[i] == b'\\' {
+            i += 2;
+            continue;
+        }
+        if rest[i..].starts_with(delimiter) {
+            // Multi-line strings may end with up to two extra quotes.
+            let mut end = i + delimiter.len();
+            while delimiter.len() == 3 && end < bytes.len() && end - i < 5 && bytes[end] == bytes[i] {
+                end += 1;
+            }
+            return Some(start + end);
+        }
+        i += 1;
     }
+    None
+}
 
-    Ok(Value::Array(result))
+fn is_quoted(value: &str) -> bool {
+    ['"', '\''].iter().any(|&quote| {
+        value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote)
+    })
+}
+
+/// Parses a basic, literal or multi-line string following TOML rules,
+/// including escape sequences.
+fn parse_string(value: &str) -> Result<Value, Box<dyn std::error::Error>> {
+    let invalid = |reason: &str| format!("Invalid string {}: {}", value, reason);
+    let document: toml::Table = format!("value = {}", value)
+        .parse()
+        .map_err(|e: toml::de::Error| invalid(e.message()))?;
+
+    match document.get("value") {
+        Some(Value::String(s)) if document.len() == 1 => Ok(Value::String(s.clone())),
+        _ => Err(invalid("expected a single quoted string").into()),
+    }
 }
 
 
@@ -109,6 +468,7 @@ mod tests {
     use toml::Table;
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_parse_simple_values() {
         assert_eq!(parse_value("42").unwrap(), Value::Integer(42));
         assert_eq!(parse_value("3.14").unwrap(), Value::Float(3.14));
@@ -166,6 +526,135 @@ mod tests {
         ]));
     }
 
+    #[test]
+    fn test_parse_integer_formats() {
+        assert_eq!(parse_value("0xFF").unwrap(), Value::Integer(255));
+        assert_eq!(parse_value("0xdead_beef").unwrap(), Value::Integer(0xdead_beef));
+        assert_eq!(parse_value("0o755").unwrap(), Value::Integer(0o755));
+        assert_eq!(parse_value("0b1010").unwrap(), Value::Integer(10));
+        assert_eq!(parse_value("1_000_000").unwrap(), Value::Integer(1_000_000));
+        assert_eq!(parse_value("+42").unwrap(), Value::Integer(42));
+        assert_eq!(parse_value("-9223372036854775808").unwrap(), Value::Integer(i64::MIN));
+
+        assert!(parse_value("0755").is_err());
+        assert!(parse_value("1__000").is_err());
+        assert!(parse_value("1_000_").is_err());
+        assert!(parse_value("0b102").is_err());
+        assert!(parse_value("0x8000000000000000").is_err());
+        assert_eq!(parse_value("-0x10").unwrap(), Value::String("-0x10".to_string()));
+    }
+
+    #[test]
+    fn test_integer_literals() {
+        let pairs = |pairs: &[(&str, &str)]| pairs.iter()
+            .map(|(path, literal)| (path.to_string(), literal.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(integer_literals(" 0x10 "), pairs(&[("", "0x10")]));
+        assert_eq!(integer_literals("{name: O'Brien, sku: 0o17, size: {width: 1_000}, tags: [1, x, 0b1]}"),
+                   pairs(&[(".sku", "0o17"), (".size.width", "1_000"), (".tags[0]", "1"), (".tags[2]", "0b1")]));
+        assert_eq!(integer_literals("{\"a.b\": 0x1}"), pairs(&[]));
+        assert_eq!(integer_literals("...[0x1]"), pairs(&[]));
+        assert_eq!(integer_literals("1.5"), pairs(&[]));
+    }
+
+    #[test]
+    fn test_parse_float_formats() {
+        assert_eq!(parse_value("1_000.5").unwrap(), Value::Float(1000.5));
+        assert_eq!(parse_value("-2E-2").unwrap(), Value::Float(-0.02));
+        assert_eq!(parse_value("5e+22").unwrap(), Value::Float(5e22));
+        assert_eq!(parse_value("+inf").unwrap(), Value::Float(f64::INFINITY));
+        assert_eq!(parse_value("-inf").unwrap(), Value::Float(f64::NEG_INFINITY));
+        assert!(parse_value("nan").unwrap().as_float().unwrap().is_nan());
+        assert!(parse_value("-nan").unwrap().as_float().unwrap().is_sign_negative());
+
+        for invalid in [".5", "5.", "NaN", "Infinity", "INF", "01.5", "1._5", "1e5_", "1.5_"] {
+            assert!(parse_value(invalid).is_err(), "{} should be rejected", invalid);
+        }
+        assert_eq!(parse_value("1.0.0").unwrap(), Value::String("1.0.0".to_string()));
+    }
+
+    #[test]
+    fn test_parse_strings() {
+        let string = |s: &str| Value::String(s.to_string());
+
+        assert_eq!(parse_value(r#""tab\there\n""#).unwrap(), string("tab\there\n"));
+        assert_eq!(parse_value(r#""\u00e9\U0001F600""#).unwrap(), string("é😀"));
+        assert_eq!(parse_value(r"'C:\Users\nodejs'").unwrap(), string(r"C:\Users\nodejs"));
+        assert_eq!(parse_value("\"\"\"\nline1\nline2\"\"\"").unwrap(), string("line1\nline2"));
+        assert_eq!(parse_value("'''\nraw \\n 'quoted'\n'''").unwrap(), string("raw \\n 'quoted'\n"));
+        assert_eq!(parse_value(r#""42""#).unwrap(), string("42"));
+        assert!(parse_value(r#""bad \q escape""#).is_err());
+    }
+
+    #[test]
+    fn test_parse_strings_in_collections() {
+        assert_eq!(parse_value(r#"["a, b", 'c]', "d\"e"]"#).unwrap(), Value::Array(vec![
+            Value::String("a, b".to_string()),
+            Value::String("c]".to_string()),
+            Value::String("d\"e".to_string()),
+        ]));
+
+        let parsed = parse_value(r#"{"key one": "x, y", list: [1, 2]}"#).unwrap();
+        let table = parsed.as_table().unwrap();
+        assert_eq!(table.get("key one"), Some(&Value::String("x, y".to_string())));
+        assert_eq!(table.get("list"), Some(&Value::Array(vec![Value::Integer(1), Value::Integer(2)])));
+
+        // A quote inside a bare word is part of it.
+        assert_eq!(parse_value("[O'Brien, Smith]").unwrap(), Value::Array(vec![
+            Value::String("O'Brien".to_string()),
+            Value::String("Smith".to_string()),
+        ]));
+        let parsed = parse_value("{name: O'Brien, nick: 'Bo, b'}").unwrap();
+        assert_eq!(parsed.get("name"), Some(&Value::String("O'Brien".to_string())));
+        assert_eq!(parsed.get("nick"), Some(&Value::String("Bo, b".to_string())));
+    }
+
+    #[test]
+    fn test_parse_content() {
+        assert_eq!(parse_content("[\n  1,\n  2,\n]\n").unwrap(), Value::Array(vec![Value::Integer(1), Value::Integer(2)]));
+        assert_eq!(parse_content("'''\nSELECT *\nFROM t\n'''").unwrap(), Value::String("SELECT *\nFROM t\n".to_string()));
+
+        let document = parse_content("[server]\nport = 8080\n").unwrap();
+        assert_eq!(document["server"]["port"], Value::Integer(8080));
+
+        let json = parse_content(r#"{"hosts": ["a", "b"], "retries": 3}"#).unwrap();
+        assert_eq!(json["hosts"], Value::Array(vec![Value::String("a".to_string()), Value::String("b".to_string())]));
+        assert_eq!(json["retries"], Value::Integer(3));
+
+        assert!(parse_content(r#"{"missing": null}"#).is_err());
+        assert!(parse_content("not a value").is_err());
+    }
+
+    #[test]
+    fn test_parse_datetimes() {
+        let parsed = parse_value("2024-05-01T10:00:00Z").unwrap();
+        assert_eq!(parsed, Value::Datetime("2024-05-01T10:00:00Z".parse().unwrap()));
+        assert_eq!(parse_value("1979-05-27T07:32:00").unwrap().as_datetime().unwrap().to_string(), "1979-05-27T07:32:00");
+        assert_eq!(parse_value("1979-05-27").unwrap().as_datetime().unwrap().to_string(), "1979-05-27");
+        assert_eq!(parse_value("07:32:00.999").unwrap().as_datetime().unwrap().to_string(), "07:32:00.999");
+        assert_eq!(parse_value("1.0.0").unwrap(), Value::String("1.0.0".to_string()));
+        assert_eq!(parse_value("\"2024-05-01\"").unwrap(), Value::String("2024-05-01".to_string()));
+
+        let now = parse_value("now").unwrap();
+        let now = now.as_datetime().unwrap();
+        assert!(now.date.is_some() && now.time.is_some());
+        assert_eq!(now.offset, Some(Offset::Z));
+    }
+
+    #[test]
+    fn test_parse_typed_values() {
+        assert_eq!(parse_typed_value("42", ValueType::String).unwrap(), Value::String("42".to_string()));
+        assert_eq!(parse_typed_value("42", ValueType::Float).unwrap(), Value::Float(42.0));
+        assert_eq!(parse_typed_value("1979-05-27", ValueType::Date).unwrap().as_datetime().unwrap().to_string(), "1979-05-27");
+        assert!(parse_typed_value("1979-05-27", ValueType::Datetime).is_err());
+        assert!(parse_typed_value("1979-05-27T07:32:00Z", ValueType::LocalDatetime).is_err());
+        assert!(parse_typed_value("yes", ValueType::Boolean).is_err());
+
+        let today = parse_typed_value("now", ValueType::Date).unwrap();
+        let today = today.as_datetime().unwrap();
+        assert!(today.date.is_some() && today.time.is_none() && today.offset.is_none());
+    }
+
     #[test]
     fn test_parse_invalid_input() {
         assert_eq!(parse_value("[1, 2, 3").unwrap(), Value::String("[1, 2, 3".to_string()));
@@ -0,0 +1,146 @@
+//! Parsing of the key syntax shared by all operations: dot separated keys with
+//! optional `[index]` / `[]` suffixes, e.g. `products[1].name` or `matrix[0][]`.
+//! A key holding dots or brackets is quoted as in TOML: `hosts."db.example.com"`.
+
+use std::borrow::Cow;
+use toml::Value;
+
+pub enum Segment<'a> {
+    Key(Cow<'a, str>),
+    /// `None` stands for the empty brackets `[]`.
+    Index(Option<usize>),
+}
+
+/// Splits a full key into its table keys and array indices.
+pub fn parse_path(key: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = key;
+    while !rest.is_empty() {
+        let (current_part, remaining) = split_first_key(rest);
+        match parse_key_part(current_part) {
+            KeyPart::Normal(key) => segments.push(Segment::Key(key)),
+            KeyPart::Array(key, indices) => {
+                segments.push(Segment::Key(key));
+                segments.extend(indices.into_iter().map(Segment::Index));
+            },
+        }
+        rest = remaining;
+    }
+    segments
+}
+
+/// Finds the value at `key` for modification; `[]` refers to the last element.
+pub fn lookup_mut<'a>(toml_value: &'a mut Value, key: &str) -> Option<&'a mut Value> {
+    let mut current = toml_value;
+    for segment in parse_path(key) {
+        current = match segment {
+            Segment::Key(key) => current.as_table_mut()?.get_mut(&*key)?,
+            Segment::Index(Some(index)) => current.as_array_mut()?.get_mut(index)?,
+            Segment::Index(None) => current.as_array_mut()?.last_mut()?,
+        };
+    }
+    Some(current)
+}
+
+/// Returns the existing array at `key`.
+pub fn array_mut<'a>(toml_value: &'a mut Value, key: &str) -> Result<&'a mut Vec<Value>, Box<dyn std::error::Error>> {
+    match lookup_mut(toml_value, key) {
+        Some(Value::Array(array)) => Ok(array),
+        Some(_) => Err(format!("{} is not an array", key).into()),
+        None => Err(format!("{} does not exist", key).into()),
+    }
+}
+
+/// Splits `ports[1]` into `("ports", Some(1))` and `ports[]` into
+/// `("ports", None)`, for operations that address a position in an array.
+pub fn split_last_index(key: &str) -> Result<(&str, Option<usize>), Box<dyn std::error::Error>> {
+    let open = key.rfind('[').filter(|_| key.ends_with(']'));
+    let open = open.ok_or_else(|| format!("{} does not end with an array index, e.g. {}[0]", key, key))?;
+    let index = &key[open + 1..key.len() - 1];
+    if index.is_empty() {
+        return Ok((&key[..open], None));
+    }
+    let index = index.trim().parse().map_err(|_| format!("Invalid array index '{}' in {}", index, key))?;
+    Ok((&key[..open], Some(index)))
+}
+
+pub enum KeyPart<'a> {
+    Normal(Cow<'a, str>),
+    Array(Cow<'a, str>, Vec<Option<usize>>),
+}
+
+pub fn parse_key_part(part: &str) -> KeyPart<'_> {
+    let (key, indices) = split_key_part(part);
+    if indices.is_empty() {
+        KeyPart::Normal(key)
+    } else {
+        KeyPart::Array(key, parse_indices(indices))
+    }
+}
+
+/// Splits one part of a key, such as `"db.example.com"[0]`, into its key,
+/// unquoted, and the brackets after it.
+pub fn split_key_part(part: &str) -> (Cow<'_, str>, &str) {
+    let end = quoted_end(part).unwrap_or(0);
+    let bracket_pos = part[end..].find('[').map_or(part.len(), |pos| end + pos);
+    (unquote(&part[..bracket_pos]), &part[bracket_pos..])
+}
+
+/// The key a quoted key stands for; other keys are taken as they are.
+fn unquote(key: &str) -> Cow<'_, str> {
+    if !key.starts_with(['"', '\'']) {
+        return Cow::Borrowed(key);
+    }
+    match key.parse::<toml_edit::Key>() {
+        Ok(parsed) => Cow::Owned(parsed.get().to_string()),
+        Err(_) => Cow::Borrowed(key),
+    }
+}
+
+/// The byte offset just past the quoted key `part` starts with, if it does.
+pub(super) fn quoted_end(part: &str) -> Option<usize> {
+    let quote = part.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let mut escaped = false;
+    for (i, c) in part.char_indices().skip(1) {
+        match c {
+            '\\' if quote == '"' && !escaped => escaped = true,
+            c if c == quote && !escaped => return Some(i + 1),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+fn parse_indices(s: &str) -> Vec<Option<usize>> {
+    let mut indices = Vec::new();
+    let mut current_index = String::new();
+    let mut in_bracket = false;
+
+    for c in s.chars() {
+        match c {
+            '[' => in_bracket = true,
+            ']' => {
+                in_bracket = false;
+                indices.push(current_index.parse().ok());
+                current_index.clear();
+            },
+            _ if in_bracket => current_index.push(c),
+            _ => {}
+        }
+    }
+    indices
+}
+
+pub fn split_first_key(key: &str) -> (&str, &str) {
+    let mut depth = 0;
+    let start = quoted_end(key).unwrap_or(0);
+    for (i, c) in key[start..].char_indices().map(|(i, c)| (start + i, c)) {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            '.' if depth == 0 => return (&key[..i], &key[i+1..]),
+            _ => {}
+        }
+    }
+    (key, "")
+}
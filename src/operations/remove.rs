@@ -1,10 +1,20 @@
 use toml::Value;
 use toml::Table;
+use super::path::{split_first_key, split_key_part};
 
 pub fn remove_value(toml_value: &mut Value, key: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let parts: Vec<&str> = key.split('.').collect();
+    let mut parts = Vec::new();
+    let mut rest = key;
+    loop {
+        let (part, remaining) = split_first_key(rest);
+        parts.push(part);
+        if remaining.is_empty() {
+            break;
+        }
+        rest = remaining;
+    }
     remove_recursive(toml_value, &parts)
-}    
+}
 
 fn remove_recursive(value: &mut Value, parts: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
     if parts.is_empty() {
@@ -21,17 +31,15 @@ fn remove_recursive(value: &mut Value, parts: &[&str]) -> Result<(), Box<dyn std
 }
 
 fn remove_from_table(table: &mut Table, part: &str, remaining_parts: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
-    if remaining_parts.is_empty() {
-        if let Some(bracket_pos) = part.find('[') {
-            let key = &part[..bracket_pos];
-            if let Some(value) = table.get_mut(key) {
-                remove_from_array_recursive(value, &part[bracket_pos..], &[])?;
-            }
+    let (key, indices) = split_key_part(part);
+    if remaining_parts.is_empty() && indices.is_empty() {
+        table.remove(&*key);
+    } else if let Some(next) = table.get_mut(&*key) {
+        if indices.is_empty() {
+            remove_recursive(next, remaining_parts)?;
         } else {
-            table.remove(part);
+            remove_from_array_recursive(next, indices, remaining_parts)?;
         }
-    } else if let Some(next) = table.get_mut(part) {
-        remove_recursive(next, remaining_parts)?;
     }
     Ok(())
 }
@@ -73,15 +81,15 @@ fn remove_from_array_recursive(value: &mut Value, part: &str, remaining_parts: &
 fn handle_array_removal(arr: &mut Vec<Value>, index_str: &str) -> Result<(), Box<dyn std::error::Error>> {
     if index_str.is_empty() {
         arr.clear();
-    } else if index_str.starts_with(':') {
-        let n: usize = index_str[1..].parse()?;
+    } else if let Some(count) = index_str.strip_prefix(':') {
+        let n: usize = count.parse()?;
         if n > arr.len() {
             arr.clear();
         } else {
             arr.truncate(arr.len() - n);
         }
-    } else if index_str.ends_with(':') {
-        let n: usize = index_str[..index_str.len()-1].parse()?;
+    } else if let Some(count) = index_str.strip_suffix(':') {
+        let n: usize = count.parse()?;
         if n >= arr.len() {
             arr.clear();
         } else {
@@ -187,10 +195,23 @@ mod tests {
         remove_value(&mut toml_value, "products[0]").unwrap();
         assert_eq!(get_value(&toml_value, "products[0].name"), "Nail");
 
+        remove_value(&mut toml_value, "products[0].name").unwrap();
+        assert_eq!(get_value(&toml_value, "products[0]"), r#"{"sku":284758393}"#);
+
         remove_value(&mut toml_value, "products").unwrap();
         assert_eq!(get_value(&toml_value, "products"), "");
     }
 
+    #[test]
+    fn test_remove_quoted_keys() {
+        let mut toml_value: Value = toml::from_str("[hosts]\n\"db.example.com\" = [1, 2]\nother = 3\n").unwrap();
+
+        remove_value(&mut toml_value, r#"hosts."db.example.com"[0]"#).unwrap();
+        assert_eq!(get_value(&toml_value, r#"hosts."db.example.com""#), "[2]");
+        remove_value(&mut toml_value, r#"hosts."db.example.com""#).unwrap();
+        assert_eq!(get_value(&toml_value, "hosts"), r#"{"other":3}"#);
+    }
+
     #[test]
     fn test_remove_nonexistent_values() {
         let mut toml_value = create_sample_toml();
@@ -0,0 +1,111 @@
+use toml::Value;
+use super::path::array_mut;
+use std::error::Error;
+
+// These return the new order of the array as the old index of each element,
+// so that the formatted document can be rearranged the same way.
+
+/// Moves the element at `from` so that it ends up at index `to`, shifting the
+/// elements in between.
+pub fn move_element(toml_value: &mut Value, key: &str, from: usize, to: usize) -> Result<Vec<usize>, Box<dyn Error>> {
+    let array = array_mut(toml_value, key)?;
+    check_index(key, array, from)?;
+    check_index(key, array, to)?;
+    let mut order: Vec<usize> = (0..array.len()).collect();
+    let element = order.remove(from);
+    order.insert(to, element);
+    Ok(rearrange(array, order))
+}
+
+pub fn swap_elements(toml_value: &mut Value, key: &str, a: usize, b: usize) -> Result<Vec<usize>, Box<dyn Error>> {
+    let array = array_mut(toml_value, key)?;
+    check_index(key, array, a)?;
+    check_index(key, array, b)?;
+    let mut order: Vec<usize> = (0..array.len()).collect();
+    order.swap(a, b);
+    Ok(rearrange(array, order))
+}
+
+pub fn reverse_array(toml_value: &mut Value, key: &str) -> Result<Vec<usize>, Box<dyn Error>> {
+    let array = array_mut(toml_value, key)?;
+    let order = (0..array.len()).rev().collect();
+    Ok(rearrange(array, order))
+}
+
+/// Puts the elements of `array` in `order`, leaving out those it does not list.
+pub(super) fn rearrange(array: &mut Vec<Value>, order: Vec<usize>) -> Vec<usize> {
+    *array = order.iter().map(|&i| array[i].clone()).collect();
+    order
+}
+
+fn check_index(key: &str, array: &[Value], index: usize) -> Result<(), Box<dyn Error>> {
+    if index >= array.len() {
+        return Err(format!("Index {} is out of bounds for {} (length {})", index, key, array.len()).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::get::get_value;
+
+    fn create_sample_toml() -> Value {
+        toml::from_str(r#"
+            priorities = ["low", "medium", "high", "urgent"]
+            matrix = [[1, 2, 3], [4, 5]]
+
+            [[products]]
+            name = "Hammer"
+
+            [[products]]
+            name = "Nail"
+
+            [[products]]
+            name = "Screw"
+        "#).unwrap()
+    }
+
+    #[test]
+    fn test_move_element() {
+        let mut toml_value = create_sample_toml();
+
+        assert_eq!(move_element(&mut toml_value, "priorities", 3, 0).unwrap(), vec![3, 0, 1, 2]);
+        assert_eq!(get_value(&toml_value, "priorities"), "[\"urgent\",\"low\",\"medium\",\"high\"]");
+
+        move_element(&mut toml_value, "priorities", 0, 2).unwrap();
+        assert_eq!(get_value(&toml_value, "priorities"), "[\"low\",\"medium\",\"urgent\",\"high\"]");
+
+        move_element(&mut toml_value, "products", 0, 2).unwrap();
+        assert_eq!(get_value(&toml_value, "products[0].name"), "Nail");
+        assert_eq!(get_value(&toml_value, "products[2].name"), "Hammer");
+
+        assert!(move_element(&mut toml_value, "priorities", 4, 0).is_err());
+        assert!(move_element(&mut toml_value, "missing", 0, 1).is_err());
+    }
+
+    #[test]
+    fn test_swap_elements() {
+        let mut toml_value = create_sample_toml();
+
+        assert_eq!(swap_elements(&mut toml_value, "matrix[0]", 0, 2).unwrap(), vec![2, 1, 0]);
+        assert_eq!(get_value(&toml_value, "matrix"), "[[3,2,1],[4,5]]");
+
+        swap_elements(&mut toml_value, "products", 0, 2).unwrap();
+        assert_eq!(get_value(&toml_value, "products[0].name"), "Screw");
+        assert_eq!(get_value(&toml_value, "products[2].name"), "Hammer");
+
+        assert!(swap_elements(&mut toml_value, "products[0].name", 0, 1).is_err());
+    }
+
+    #[test]
+    fn test_reverse_array() {
+        let mut toml_value = create_sample_toml();
+
+        assert_eq!(reverse_array(&mut toml_value, "matrix[1]").unwrap(), vec![1, 0]);
+        assert_eq!(get_value(&toml_value, "matrix"), "[[1,2,3],[5,4]]");
+
+        reverse_array(&mut toml_value, "products").unwrap();
+        assert_eq!(get_value(&toml_value, "products[0].name"), "Screw");
+    }
+}
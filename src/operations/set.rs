@@ -1,42 +1,60 @@
 use toml::{Value, Table};
 use super::parse::parse_value;
+use super::path::{lookup_mut, parse_key_part, split_first_key, KeyPart};
 use std::error::Error;
 
 pub fn set_value(toml_value: &mut Value, key: &str, value: &str) -> Result<(), Box<dyn Error>> {
-    set_value_recursive(toml_value, key, value)
+    let spread = value.trim().starts_with("...");
+    set_parsed_value(toml_value, key, parse_value(value)?, spread)
 }
 
-fn set_value_recursive(current: &mut Value, key: &str, value: &str) -> Result<(), Box<dyn Error>> {
+/// Sets an already parsed value. `spread` mirrors the `...[a, b]` syntax of
+/// `set_value`: on an append (`key[]`) the elements of `value` are added one by one.
+pub fn set_parsed_value(toml_value: &mut Value, key: &str, value: Value, spread: bool) -> Result<(), Box<dyn Error>> {
+    set_value_recursive(toml_value, key, &value, spread)
+}
+
+/// Returns the array at `key`, creating it like `set_value` would when it does
+/// not exist yet.
+pub(super) fn array_at<'a>(toml_value: &'a mut Value, key: &str) -> Result<&'a mut Vec<Value>, Box<dyn Error>> {
+    if lookup_mut(toml_value, key).is_none() {
+        set_parsed_value(toml_value, key, Value::Array(Vec::new()), false)?;
+    }
+    lookup_mut(toml_value, key)
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| format!("{} is not an array", key).into())
+}
+
+fn set_value_recursive(current: &mut Value, key: &str, value: &Value, spread: bool) -> Result<(), Box<dyn Error>> {
     let (current_part, remaining) = split_first_key(key);
     match parse_key_part(current_part) {
-        KeyPart::Normal(key) => handle_normal_key(current, key, remaining, value),
+        KeyPart::Normal(key) => handle_normal_key(current, &key, remaining, value, spread),
         KeyPart::Array(key, indices) => {
-            if indices.is_empty() && value.trim().starts_with("...") {
-                handle_array_spread(current, key, value)
+            if indices.is_empty() && spread {
+                handle_array_spread(current, &key, value)
             } else {
-                handle_array_key(current, key, indices, remaining, value)
+                handle_array_key(current, &key, indices, remaining, value, spread)
             }
         },
     }
 }
 
-fn handle_array_spread(current: &mut Value, key: &str, value: &str) -> Result<(), Box<dyn Error>> {
+fn handle_array_spread(current: &mut Value, key: &str, value: &Value) -> Result<(), Box<dyn Error>> {
     ensure_table(current);
     let table = current.as_table_mut().unwrap();
     if !table.contains_key(key) {
         table.insert(key.to_string(), Value::Array(Vec::new()));
     }
     let array = table.get_mut(key).unwrap().as_array_mut().unwrap();
-    let parsed_value = parse_value(value)?;
-    if let Value::Array(inner_array) = parsed_value {
-        array.extend(inner_array);
+    if let Value::Array(inner_array) = value {
+        array.extend(inner_array.iter().cloned());
     } else {
         return Err("Expected an array after spread operator".into());
     }
     Ok(())
 }
 
-fn handle_normal_key(current: &mut Value, key: &str, remaining: &str, value: &str) -> Result<(), Box<dyn Error>> {
+fn handle_normal_key(current: &mut Value, key: &str, remaining: &str, value: &Value, spread: bool) -> Result<(), Box<dyn Error>> {
     ensure_table(current);
     let table = current.as_table_mut().unwrap();
     if !table.contains_key(key) {
@@ -44,14 +62,14 @@ fn handle_normal_key(current: &mut Value, key: &str, remaining: &str, value: &st
     }
     let next = table.get_mut(key).unwrap();
     if remaining.is_empty() {
-        *next = parse_value(value)?;
+        *next = value.clone();
     } else {
-        set_value_recursive(next, remaining, value)?;
+        set_value_recursive(next, remaining, value, spread)?;
     }
     Ok(())
 }
 
-fn handle_array_key(current: &mut Value, key: &str, indices: Vec<Option<usize>>, remaining: &str, value: &str) -> Result<(), Box<dyn Error>> {
+fn handle_array_key(current: &mut Value, key: &str, indices: Vec<Option<usize>>, remaining: &str, value: &Value, spread: bool) -> Result<(), Box<dyn Error>> {
     ensure_table(current);
     let table = current.as_table_mut().unwrap();
     if !table.contains_key(key) {
@@ -65,7 +83,7 @@ fn handle_array_key(current: &mut Value, key: &str, indices: Vec<Option<usize>>,
                 current_value = handle_specific_index(current_value, *index)?;
             },
             None => {
-                handle_append_index(current_value, i == indices.len() - 1 && remaining.is_empty(), value)?;
+                handle_append_index(current_value, i == indices.len() - 1 && remaining.is_empty(), value, spread)?;
                 return Ok(());  // We're done after appending
             },
         };
@@ -73,10 +91,10 @@ fn handle_array_key(current: &mut Value, key: &str, indices: Vec<Option<usize>>,
     
 
     // Only set the value if it's not a spread operation and there's no remaining key
-    if remaining.is_empty() && !value.trim().starts_with("...") {
-        *current_value = parse_value(value)?;
+    if remaining.is_empty() && !spread {
+        *current_value = value.clone();
     } else if !remaining.is_empty() {
-        set_value_recursive(current_value, remaining, value)?;
+        set_value_recursive(current_value, remaining, value, spread)?;
     }
     Ok(())
 }
@@ -91,22 +109,20 @@ fn handle_specific_index(value: &mut Value, index: usize) -> Result<&mut Value,
     Ok(&mut array[index])
 }
 
-fn handle_append_index(value: &mut Value, is_last: bool, new_value: &str) -> Result<(), Box<dyn Error>> {
+fn handle_append_index(value: &mut Value, is_last: bool, new_value: &Value, spread: bool) -> Result<(), Box<dyn Error>> {
     
     ensure_array(value);
     let array = value.as_array_mut().unwrap();
     
     if is_last {
-        if new_value.trim().starts_with("...") {
-            let parsed_value = parse_value(&new_value[3..])?;
-            if let Value::Array(inner_array) = parsed_value {
-                array.extend(inner_array);
+        if spread {
+            if let Value::Array(inner_array) = new_value {
+                array.extend(inner_array.iter().cloned());
             } else {
                 return Err("Expected an array after spread operator".into());
             }
         } else {
-            let parsed_value = parse_value(new_value)?;
-            array.push(parsed_value);
+            array.push(new_value.clone());
         }
     } else {
         array.push(Value::Array(Vec::new()));
@@ -116,54 +132,6 @@ fn handle_append_index(value: &mut Value, is_last: bool, new_value: &str) -> Res
 }
 
 
-enum KeyPart<'a> {
-    Normal(&'a str),
-    Array(&'a str, Vec<Option<usize>>),
-}
-
-fn parse_key_part(part: &str) -> KeyPart {
-    if let Some(bracket_pos) = part.find('[') {
-        let key = &part[..bracket_pos];
-        let indices = parse_indices(&part[bracket_pos..]);
-        KeyPart::Array(key, indices)
-    } else {
-        KeyPart::Normal(part)
-    }
-}
-
-fn parse_indices(s: &str) -> Vec<Option<usize>> {
-    let mut indices = Vec::new();
-    let mut current_index = String::new();
-    let mut in_bracket = false;
-
-    for c in s.chars() {
-        match c {
-            '[' => in_bracket = true,
-            ']' => {
-                in_bracket = false;
-                indices.push(current_index.parse().ok());
-                current_index.clear();
-            },
-            _ if in_bracket => current_index.push(c),
-            _ => {}
-        }
-    }
-    indices
-}
-
-fn split_first_key(key: &str) -> (&str, &str) {
-    let mut depth = 0;
-    for (i, c) in key.char_indices() {
-        match c {
-            '[' => depth += 1,
-            ']' => depth -= 1,
-            '.' if depth == 0 => return (&key[..i], &key[i+1..]),
-            _ => {}
-        }
-    }
-    (key, "")
-}
-
 fn ensure_table(value: &mut Value) {
     if !value.is_table() {
         *value = Value::Table(Table::new());
@@ -245,6 +213,9 @@ mod tests {
 
         set_value(&mut toml_value, "deep.nested.value", "42").unwrap();
         assert_eq!(get_value(&toml_value, "deep.nested.value"), "42");
+
+        set_value(&mut toml_value, r#"hosts."db.example.com".ports[]"#, "5432").unwrap();
+        assert_eq!(toml_value["hosts"]["db.example.com"]["ports"], Value::Array(vec![Value::Integer(5432)]));
     }
 
     #[test]
@@ -0,0 +1,288 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use toml::Value;
+use toml::value::{Datetime, Offset};
+use super::get::lookup;
+use super::path::array_mut;
+use super::reorder::rearrange;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SortOrder {
+    /// Numbers by value, everything else by its text.
+    #[default]
+    Lexical,
+    /// Digit runs inside strings compare as numbers: `item2` < `item10`.
+    Natural,
+    /// Strings holding numbers compare by their numeric value.
+    Numeric,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SortOptions {
+    pub order: SortOrder,
+    pub reverse: bool,
+    pub unique: bool,
+    /// For arrays of tables: the key inside each table to sort by.
+    pub by: Option<String>,
+}
+
+/// Sorts the array at `key` and returns its new order as the old index of each
+/// element kept.
+pub fn sort_array(toml_value: &mut Value, key: &str, options: &SortOptions) -> Result<Vec<usize>, Box<dyn Error>> {
+    let array = array_mut(toml_value, key)?;
+    check_sort_keys(array, options)?;
+
+    let mut order: Vec<usize> = (0..array.len()).collect();
+    order.sort_by(|&a, &b| compare_elements(&array[a], &array[b], options));
+    if options.unique {
+        order.dedup_by(|&mut a, &mut b| compare_elements(&array[a], &array[b], options) == Ordering::Equal);
+    }
+    Ok(rearrange(array, order))
+}
+
+/// Whether the array at `key` is already sorted (and free of duplicates when
+/// `unique` is set), i.e. whether `sort_array` would leave it unchanged.
+pub fn is_sorted(toml_value: &Value, key: &str, options: &SortOptions) -> Result<bool, Box<dyn Error>> {
+    let array = match lookup(toml_value, key) {
+        Some(Value::Array(array)) => array,
+        Some(_) => return Err(format!("{} is not an array", key).into()),
+        None => return Err(format!("{} does not exist", key).into()),
+    };
+    check_sort_keys(array, options)?;
+
+    Ok(array.windows(2).all(|pair| match compare_elements(&pair[0], &pair[1], options) {
+        Ordering::Less => true,
+        Ordering::Equal => !options.unique,
+        Ordering::Greater => false,
+    }))
+}
+
+fn check_sort_keys(array: &[Value], options: &SortOptions) -> Result<(), Box<dyn Error>> {
+    if options.by.is_some() && !array.iter().all(Value::is_table) {
+        return Err("--by can only be used on arrays of tables".into());
+    }
+    Ok(())
+}
+
+fn compare_elements(a: &Value, b: &Value, options: &SortOptions) -> Ordering {
+    let compare = |a, b| {
+        let ordering = compare_values(a, b, options.order);
+        if options.reverse { ordering.reverse() } else { ordering }
+    };
+    match &options.by {
+        // Tables without the field go last, in either direction.
+        Some(field) => match (lookup(a, field), lookup(b, field)) {
+            (Some(a), Some(b)) => compare(a, b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        },
+        None => compare(a, b),
+    }
+}
+
+fn compare_values(a: &Value, b: &Value, order: SortOrder) -> Ordering {
+    if order == SortOrder::Numeric {
+        match (as_number(a, true), as_number(b, true)) {
+            (Some(x), Some(y)) => return x.total_cmp(&y),
+            (Some(_), None) => return Ordering::Less,
+            (None, Some(_)) => return Ordering::Greater,
+            (None, None) => {},
+        }
+    }
+
+    match (a, b) {
+        (Value::String(x), Value::String(y)) if order == SortOrder::Natural => natural_cmp(x, y).then_with(|| x.cmp(y)),
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Boolean(x), Value::Boolean(y)) => x.cmp(y),
+        (Value::Datetime(x), Value::Datetime(y)) => instant(x).cmp(&instant(y)).then_with(|| x.to_string().cmp(&y.to_string())),
+        _ => match (as_number(a, false), as_number(b, false)) {
+            (Some(x), Some(y)) => x.total_cmp(&y),
+            _ => type_rank(a).cmp(&type_rank(b)).then_with(|| text(a).cmp(&text(b))),
+        },
+    }
+}
+
+/// The seconds since 1970-01-01T00:00:00Z and the nanoseconds a datetime
+/// stands for, so that offsets are taken into account. Local datetimes are
+/// taken as UTC, a date alone as its midnight and a time alone as on 1970-01-01.
+fn instant(datetime: &Datetime) -> (i64, u32) {
+    let days = datetime.date.map_or(0, |date| days_from_civil(date.year.into(), date.month.into(), date.day.into()));
+    let (seconds, nanoseconds) = datetime.time.map_or((0, 0), |time| {
+        (i64::from(time.hour) * 3_600 + i64::from(time.minute) * 60 + i64::from(time.second), time.nanosecond)
+    });
+    let offset = match datetime.offset {
+        Some(Offset::Custom { minutes }) => i64::from(minutes) * 60,
+        _ => 0,
+    };
+    (days * 86_400 + seconds - offset, nanoseconds)
+}
+
+/// Days since 1970-01-01 (Howard Hinnant's algorithm).
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn as_number(value: &Value, parse_strings: bool) -> Option<f64> {
+    match value {
+        Value::Integer(i) => Some(*i as f64),
+        Value::Float(f) => Some(*f),
+        Value::String(s) if parse_strings => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn type_rank(value: &Value) -> u8 {
+    match value {
+        Value::Boolean(_) => 0,
+        Value::Integer(_) | Value::Float(_) => 1,
+        Value::Datetime(_) => 2,
+        Value::String(_) => 3,
+        Value::Array(_) => 4,
+        Value::Table(_) => 5,
+    }
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        _ => serde_json::to_string(value).unwrap_or_default(),
+    }
+}
+
+/// Compares strings chunk by chunk, treating runs of ASCII digits as numbers.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        match (a.chars().next(), b.chars().next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x_digits, x_rest) = split_digits(a);
+                let (y_digits, y_rest) = split_digits(b);
+                let (x_digits, y_digits) = (x_digits.trim_start_matches('0'), y_digits.trim_start_matches('0'));
+                let ordering = x_digits.len().cmp(&y_digits.len()).then_with(|| x_digits.cmp(y_digits));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a = x_rest;
+                b = y_rest;
+            },
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a = &a[x.len_utf8()..];
+                b = &b[y.len_utf8()..];
+            },
+        }
+    }
+}
+
+fn split_digits(s: &str) -> (&str, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::get::get_value;
+
+    fn create_sample_toml() -> Value {
+        toml::from_str(r#"
+            allowed_hosts = ["web10", "db", "web2", "db", "api"]
+            numbers = ["10", "9", "100", "9.5"]
+
+            [[products]]
+            name = "Nail"
+            price = 2
+
+            [[products]]
+            name = "Hammer"
+            price = 10
+
+            [[products]]
+            name = "Bolt"
+        "#).unwrap()
+    }
+
+    #[test]
+    fn test_sort_strings() {
+        let mut toml_value = create_sample_toml();
+
+        sort_array(&mut toml_value, "allowed_hosts", &SortOptions::default()).unwrap();
+        assert_eq!(get_value(&toml_value, "allowed_hosts"), r#"["api","db","db","web10","web2"]"#);
+
+        let natural = SortOptions { order: SortOrder::Natural, unique: true, ..Default::default() };
+        assert_eq!(sort_array(&mut toml_value, "allowed_hosts", &natural).unwrap(), vec![0, 1, 4, 3]);
+        assert_eq!(get_value(&toml_value, "allowed_hosts"), r#"["api","db","web2","web10"]"#);
+
+        let reverse = SortOptions { reverse: true, ..Default::default() };
+        sort_array(&mut toml_value, "allowed_hosts", &reverse).unwrap();
+        assert_eq!(get_value(&toml_value, "allowed_hosts"), r#"["web2","web10","db","api"]"#);
+    }
+
+    #[test]
+    fn test_sort_numeric() {
+        let mut toml_value = create_sample_toml();
+
+        let numeric = SortOptions { order: SortOrder::Numeric, ..Default::default() };
+        sort_array(&mut toml_value, "numbers", &numeric).unwrap();
+        assert_eq!(get_value(&toml_value, "numbers"), r#"["9","9.5","10","100"]"#);
+    }
+
+    #[test]
+    fn test_sort_array_of_tables_by_field() {
+        let mut toml_value = create_sample_toml();
+
+        let by_name = SortOptions { by: Some("name".to_string()), ..Default::default() };
+        sort_array(&mut toml_value, "products", &by_name).unwrap();
+        assert_eq!(get_value(&toml_value, "products[0].name"), "Bolt");
+        assert_eq!(get_value(&toml_value, "products[2].name"), "Nail");
+
+        let by_price = SortOptions { by: Some("price".to_string()), ..Default::default() };
+        sort_array(&mut toml_value, "products", &by_price).unwrap();
+        assert_eq!(get_value(&toml_value, "products[0].name"), "Nail");
+        assert_eq!(get_value(&toml_value, "products[1].name"), "Hammer");
+        assert_eq!(get_value(&toml_value, "products[2].name"), "Bolt");
+
+        assert!(sort_array(&mut toml_value, "allowed_hosts", &by_name).is_err());
+
+        // Bolt has no price and stays last when reversed too.
+        let by_price = SortOptions { reverse: true, ..by_price };
+        sort_array(&mut toml_value, "products", &by_price).unwrap();
+        assert_eq!(get_value(&toml_value, "products[0].name"), "Hammer");
+        assert_eq!(get_value(&toml_value, "products[1].name"), "Nail");
+        assert_eq!(get_value(&toml_value, "products[2].name"), "Bolt");
+    }
+
+    #[test]
+    fn test_sort_datetimes() {
+        let mut toml_value: Value = toml::from_str(r#"
+            times = [2024-05-01T10:00:00+02:00, 2024-05-01T09:00:00Z, 2024-04-30, 1969-12-31T23:59:59Z]
+        "#).unwrap();
+
+        assert_eq!(sort_array(&mut toml_value, "times", &SortOptions::default()).unwrap(), vec![3, 2, 0, 1]);
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+    }
+
+    #[test]
+    fn test_is_sorted() {
+        let mut toml_value = create_sample_toml();
+        let unique = SortOptions { unique: true, ..Default::default() };
+
+        assert!(!is_sorted(&toml_value, "allowed_hosts", &SortOptions::default()).unwrap());
+        sort_array(&mut toml_value, "allowed_hosts", &SortOptions::default()).unwrap();
+        assert!(is_sorted(&toml_value, "allowed_hosts", &SortOptions::default()).unwrap());
+        assert!(!is_sorted(&toml_value, "allowed_hosts", &unique).unwrap());
+        assert!(is_sorted(&toml_value, "missing", &unique).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use toml::{Table, Value};
+use super::get::lookup;
+use super::parse::parse_value;
+use super::set::array_at;
+use std::error::Error;
+
+pub fn upsert_value(toml_value: &mut Value, key: &str, value: &str, match_keys: &[String]) -> Result<usize, Box<dyn Error>> {
+    upsert_parsed_value(toml_value, key, parse_value(value)?, match_keys)
+}
+
+/// Finds the table in the array at `key` whose `match_keys` fields equal those
+/// of `value` and merges the fields of `value` into it. When no entry matches,
+/// `value` is appended as a new table. Returns the index of the entry.
+pub fn upsert_parsed_value(toml_value: &mut Value, key: &str, value: Value, match_keys: &[String]) -> Result<usize, Box<dyn Error>> {
+    let Value::Table(fields) = value else {
+        return Err("Upsert value must be a table, e.g. '{name: \"Nail\", sku: 1}'".into());
+    };
+    if match_keys.is_empty() {
+        return Err("Upsert requires at least one key field".into());
+    }
+    let fields = Value::Table(fields);
+    for match_key in match_keys {
+        if lookup(&fields, match_key).is_none() {
+            return Err(format!("Upsert value is missing the key field {}", match_key).into());
+        }
+    }
+
+    let array = array_at(toml_value, key)?;
+    let matches: Vec<usize> = array.iter()
+        .enumerate()
+        .filter(|(_, element)| element.is_table() && match_keys.iter().all(|k| lookup(element, k) == lookup(&fields, k)))
+        .map(|(i, _)| i)
+        .collect();
+
+    match matches[..] {
+        [] => {
+            array.push(fields);
+            Ok(array.len() - 1)
+        },
+        [index] => {
+            merge_tables(array[index].as_table_mut().unwrap(), fields);
+            Ok(index)
+        },
+        _ => Err(format!("{} entries of {} match the key fields", matches.len(), key).into()),
+    }
+}
+
+/// Copies `fields` into `target`; nested tables are merged rather than replaced.
+fn merge_tables(target: &mut Table, fields: Value) {
+    let Value::Table(fields) = fields else { return };
+    for (field, value) in fields {
+        match (target.get_mut(&field), value) {
+            (Some(Value::Table(existing)), value @ Value::Table(_)) => merge_tables(existing, value),
+            (_, value) => {
+                target.insert(field, value);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::get::get_value;
+
+    fn create_sample_toml() -> Value {
+        toml::from_str(r#"
+            [[products]]
+            name = "Hammer"
+            sku = 738594937
+
+            [[products]]
+            name = "Nail"
+            sku = 284758393
+            dimensions = { length = 5, width = 1 }
+        "#).unwrap()
+    }
+
+    fn keys(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_upsert_existing_entry() {
+        let mut toml_value = create_sample_toml();
+
+        upsert_value(&mut toml_value, "products", r#"{name: "Nail", sku: 1, color: "gray"}"#, &keys(&["name"])).unwrap();
+        assert_eq!(get_value(&toml_value, "products[1].sku"), "1");
+        assert_eq!(get_value(&toml_value, "products[1].color"), "gray");
+        assert_eq!(get_value(&toml_value, "products[0].sku"), "738594937");
+
+        upsert_value(&mut toml_value, "products", r#"{name: "Nail", dimensions: {width: 2}}"#, &keys(&["name"])).unwrap();
+        assert_eq!(get_value(&toml_value, "products[1].dimensions.length"), "5");
+        assert_eq!(get_value(&toml_value, "products[1].dimensions.width"), "2");
+    }
+
+    #[test]
+    fn test_upsert_appends_new_entry() {
+        let mut toml_value = create_sample_toml();
+
+        upsert_value(&mut toml_value, "products", r#"{name: "Nail", sku: 1}"#, &keys(&["name", "sku"])).unwrap();
+        assert_eq!(get_value(&toml_value, "products[2].name"), "Nail");
+        assert_eq!(get_value(&toml_value, "products[1].sku"), "284758393");
+
+        upsert_value(&mut toml_value, "store.tools", r#"{name: "Saw"}"#, &keys(&["name"])).unwrap();
+        assert_eq!(get_value(&toml_value, "store.tools[0].name"), "Saw");
+    }
+
+    #[test]
+    fn test_upsert_invalid() {
+        let mut toml_value = create_sample_toml();
+
+        assert!(upsert_value(&mut toml_value, "products", "Nail", &keys(&["name"])).is_err());
+        assert!(upsert_value(&mut toml_value, "products", r#"{sku: 1}"#, &keys(&["name"])).is_err());
+        assert!(upsert_value(&mut toml_value, "products", r#"{name: "Nail"}"#, &[]).is_err());
+        assert!(upsert_value(&mut toml_value, "products[0].name", r#"{name: "Nail"}"#, &keys(&["name"])).is_err());
+
+        upsert_value(&mut toml_value, "products", r#"{name: "Nail", sku: 2}"#, &keys(&["name", "sku"])).unwrap();
+        assert!(upsert_value(&mut toml_value, "products", r#"{name: "Nail"}"#, &keys(&["name"])).is_err());
+    }
+}
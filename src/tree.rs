@@ -0,0 +1,146 @@
+//! `ctoml tree`: an outline of a document or subtree, in the order the keys
+//! are written in. Whether a table is a `[header]` table, an inline table, a
+//! dotted key or an element of `[[array of tables]]` is shown along with it.
+
+use std::error::Error;
+use toml::Value;
+use toml_edit::{DocumentMut, Item, Table};
+
+use crate::document::{node, Node};
+use crate::operations;
+
+/// Renders the tree below `key` (the whole document when empty), down to
+/// `depth` levels below it when given.
+pub fn render(document: &DocumentMut, key: &str, depth: Option<usize>) -> Result<String, Box<dyn Error>> {
+    let node = if key.is_empty() {
+        Node::Table(document.as_table())
+    } else {
+        node(document, key).ok_or_else(|| format!("{} does not exist", key))?
+    };
+
+    let mut lines = vec![format!("{}: {}", if key.is_empty() { "." } else { key }, describe(&node))];
+    render_children(node, "", depth, &mut lines);
+    Ok(lines.join("\n"))
+}
+
+fn render_children(node: Node, indent: &str, depth: Option<usize>, lines: &mut Vec<String>) {
+    if depth == Some(0) {
+        return;
+    }
+    let children: Vec<(String, Node)> = match node {
+        Node::Table(table) | Node::Item(Item::Table(table)) => table.iter()
+            .filter(|(_, item)| !item.is_none())
+            .map(|(key, item)| (key.to_string(), Node::from(item)))
+            .collect(),
+        Node::Item(Item::ArrayOfTables(array)) => array.iter()
+            .enumerate()
+            .map(|(i, table)| (format!("[{}]", i), Node::Table(table)))
+            .collect(),
+        Node::Value(toml_edit::Value::InlineTable(table)) => table.iter()
+            .map(|(key, value)| (key.to_string(), Node::Value(value)))
+            .collect(),
+        Node::Value(toml_edit::Value::Array(array)) => array.iter()
+            .enumerate()
+            .map(|(i, value)| (format!("[{}]", i), Node::Value(value)))
+            .collect(),
+        _ => return,
+    };
+
+    let count = children.len();
+    for (i, (name, node)) in children.into_iter().enumerate() {
+        let last = i + 1 == count;
+        lines.push(format!("{}{}{}: {}", indent, if last { "└── " } else { "├── " }, name, describe(&node)));
+        let indent = format!("{}{}", indent, if last { "    " } else { "│   " });
+        render_children(node, &indent, depth.map(|depth| depth - 1), lines);
+    }
+}
+
+fn describe(node: &Node) -> String {
+    let keys = |len: usize| if len == 1 { "key" } else { "keys" };
+    let table = |table: &Table| {
+        let style = if table.is_dotted() { "dotted keys" } else { "table" };
+        format!("{} ({} {})", style, table.len(), keys(table.len()))
+    };
+    match node {
+        Node::Table(t) => table(t),
+        Node::Item(Item::Table(t)) => table(t),
+        Node::Item(Item::ArrayOfTables(array)) => format!("array of tables ({})", array.len()),
+        Node::Item(_) => String::new(),
+        Node::Value(toml_edit::Value::InlineTable(table)) => format!("inline table ({} {})", table.len(), keys(table.len())),
+        Node::Value(toml_edit::Value::Array(array)) => format!("array ({})", array.len()),
+        Node::Value(toml_edit::Value::String(s)) => format!("string = {}", serde_json::to_string(s.value()).unwrap_or_default()),
+        // The value as written in the file, e.g. `0xFF` rather than `255`.
+        Node::Value(value) => format!("{} = {}", scalar_type(value), (*value).clone().decorated("", "")),
+    }
+}
+
+fn scalar_type(value: &toml_edit::Value) -> &'static str {
+    match value {
+        toml_edit::Value::Integer(_) => "integer",
+        toml_edit::Value::Float(_) => "float",
+        toml_edit::Value::Boolean(_) => "boolean",
+        toml_edit::Value::Datetime(dt) => operations::type_name(&Value::Datetime(*dt.value())),
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_sample_document() -> DocumentMut {
+        let content = r#"
+food.snickers.sweet = true
+
+[foo]
+bar = "some string"
+mode = 0o755
+integers = [1, 2]
+name = { first = "Tom" }
+
+[[products]]
+name = "Hammer"
+released = 2024-05-01
+"#;
+        content.parse().unwrap()
+    }
+
+    #[test]
+    fn test_render_document() {
+        let document = create_sample_document();
+
+        assert_eq!(render(&document, "", None).unwrap(), [
+            ".: table (3 keys)",
+            "├── food: dotted keys (1 key)",
+            "│   └── snickers: dotted keys (1 key)",
+            "│       └── sweet: boolean = true",
+            "├── foo: table (4 keys)",
+            "│   ├── bar: string = \"some string\"",
+            "│   ├── mode: integer = 0o755",
+            "│   ├── integers: array (2)",
+            "│   │   ├── [0]: integer = 1",
+            "│   │   └── [1]: integer = 2",
+            "│   └── name: inline table (1 key)",
+            "│       └── first: string = \"Tom\"",
+            "└── products: array of tables (1)",
+            "    └── [0]: table (2 keys)",
+            "        ├── name: string = \"Hammer\"",
+            "        └── released: date = 2024-05-01",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn test_render_subtree_with_depth() {
+        let document = create_sample_document();
+
+        assert_eq!(render(&document, "foo", Some(1)).unwrap(), [
+            "foo: table (4 keys)",
+            "├── bar: string = \"some string\"",
+            "├── mode: integer = 0o755",
+            "├── integers: array (2)",
+            "└── name: inline table (1 key)",
+        ].join("\n"));
+        assert_eq!(render(&document, "products[0].name", None).unwrap(), "products[0].name: string = \"Hammer\"");
+        assert!(render(&document, "missing", None).is_err());
+    }
+}
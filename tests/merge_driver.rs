@@ -0,0 +1,93 @@
+//! `ctoml merge3` registered as a git merge driver in a throwaway repository.
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn git(repo: &Path, args: &[&str]) -> Output {
+    Command::new("git")
+        .args(["-c", "user.name=ctoml", "-c", "user.email=ctoml@example.com", "-c", "init.defaultBranch=main"])
+        .args(args)
+        .current_dir(repo)
+        .output()
+        .expect("git must be installed to run this test")
+}
+
+fn git_ok(repo: &Path, args: &[&str]) {
+    let output = git(repo, args);
+    assert!(output.status.success(), "git {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+}
+
+/// A repository with `config.toml` committed on `main`, and branches `ours`
+/// and `theirs` each applying one edit to it.
+fn create_repository(base: &str, ours: &str, theirs: &str) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = dir.path();
+    git_ok(repo, &["init", "-q"]);
+    let driver = format!("{} merge3 %O %A %B", env!("CARGO_BIN_EXE_ctoml"));
+    git_ok(repo, &["config", "merge.ctoml.driver", &driver]);
+    fs::write(repo.join(".gitattributes"), "*.toml merge=ctoml\n").unwrap();
+
+    for (branch, content) in [("main", base), ("ours", ours), ("theirs", theirs)] {
+        if branch != "main" {
+            git_ok(repo, &["checkout", "-q", "-b", branch, "main"]);
+        }
+        fs::write(repo.join("config.toml"), content).unwrap();
+        git_ok(repo, &["add", "."]);
+        git_ok(repo, &["commit", "-q", "-m", branch]);
+    }
+    git_ok(repo, &["checkout", "-q", "ours"]);
+    dir
+}
+
+const BASE: &str = r#"# Shared settings
+[package]
+name = "service"
+version = "1.0.0"
+
+[dependencies]
+toml = "0.8"
+"#;
+
+#[test]
+fn merges_changes_to_different_keys() {
+    let ours = BASE.replace("version = \"1.0.0\"", "version = \"1.1.0\"");
+    let theirs = BASE.replace("toml = \"0.8\"", "toml = \"0.8\"\nregex = \"1\"");
+    let dir = create_repository(BASE, &ours, &theirs);
+
+    let output = git(dir.path(), &["merge", "-q", "--no-edit", "theirs"]);
+    assert!(output.status.success(), "merge failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read_to_string(dir.path().join("config.toml")).unwrap(), r#"# Shared settings
+[package]
+name = "service"
+version = "1.1.0"
+
+[dependencies]
+toml = "0.8"
+regex = "1"
+"#);
+}
+
+#[test]
+fn marks_conflicting_keys_only() {
+    let ours = BASE.replace("version = \"1.0.0\"", "version = \"1.1.0\"");
+    let theirs = BASE
+        .replace("version = \"1.0.0\"", "version = \"2.0.0\"")
+        .replace("toml = \"0.8\"", "toml = \"0.9\"");
+    let dir = create_repository(BASE, &ours, &theirs);
+
+    let output = git(dir.path(), &["merge", "-q", "--no-edit", "theirs"]);
+    assert!(!output.status.success());
+    assert_eq!(fs::read_to_string(dir.path().join("config.toml")).unwrap(), r#"# Shared settings
+[package]
+name = "service"
+<<<<<<< ours
+version = "1.1.0"
+=======
+version = "2.0.0"
+>>>>>>> theirs
+
+[dependencies]
+toml = "0.9"
+"#);
+}